@@ -3,3 +3,20 @@ mod set1;
 #[cfg(test)]
 #[allow(dead_code)]
 mod set2;
+#[cfg(test)]
+mod set3;
+#[cfg(test)]
+mod set4;
+
+/// Returns the key source of a challenge, seeded from `CRYPT_SEED` when it
+/// is set, else with a random seed. The seed is printed, which the test
+/// harness shows if the challenge fails, so that the run can be replayed.
+#[cfg(test)]
+fn key_source() -> crypt::gen::SeededSource {
+    let source = match crypt::gen::SeededSource::from_env() {
+        Ok(source) => source.unwrap_or_else(crypt::gen::SeededSource::random),
+        Err(err) => panic!("{}", err),
+    };
+    println!("{}={}", crypt::gen::SEED_VAR, source.seed());
+    source
+}
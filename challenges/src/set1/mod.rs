@@ -3,6 +3,7 @@ use crypt::encoding::base64::*;
 use crypt::encoding::hex::*;
 use crypt::encoding::*;
 use crypt::op::xor;
+use crypt::score::Ngrams;
 use crypt::util::read_lines;
 use crypt::{Hacker, Result};
 use std::str::from_utf8;
@@ -40,9 +41,15 @@ fn challenge_2() -> Result<()> {
 #[test]
 fn challenge_3() {
     let input_hex = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
-    let hacker = Hacker::new();
-    let message = hacker.crack_single_char_xor(input_hex).unwrap();
-    assert_eq!("Cooking MC's like a pound of bacon", message);
+    let hackers = [
+        Hacker::new(),
+        Hacker::with_scorer(Ngrams::bigrams()),
+        Hacker::with_scorer(Ngrams::trigrams()),
+    ];
+    for hacker in hackers {
+        let message = hacker.crack_single_char_xor(input_hex).unwrap();
+        assert_eq!("Cooking MC's like a pound of bacon", message);
+    }
 }
 
 #[test]
@@ -57,8 +64,20 @@ fn challenge_4() {
 
     assert!(results.len() > 0);
 
-    let message = hacker.max_letter_score(&results).unwrap();
+    let message = hacker.max_score(&results).unwrap();
     assert_eq!("Now that the party is jumping\n", message);
+
+    // The same search, on bytes
+    let lines = lines
+        .iter()
+        .map(|line| hacker.hex.decode(line))
+        .collect::<Result<Vec<Vec<u8>>>>()
+        .unwrap();
+    let found = hacker.find_single_byte_xor(&lines, 5);
+    let (index, candidate) = &found[0];
+    assert_eq!(170, *index);
+    assert_eq!(0x35, candidate.key);
+    assert_eq!(b"Now that the party is jumping\n", &candidate.plaintext[..]);
 }
 
 #[test]
@@ -80,14 +99,21 @@ fn challenge_5() {
 fn challenge_6() -> Result<()> {
     let lines = read_lines("data/set1_challenge6.txt")?;
     let encrypted_b64 = lines.join("");
-    let hacker = Hacker::new();
+    for hacker in [Hacker::new(), Hacker::with_scorer(Ngrams::bigrams())] {
+        let encrypted_plain = hacker.b64.decode(&encrypted_b64)?;
+        let encrypted_plain = from_utf8(&encrypted_plain)?;
+        let (message, key) = hacker.break_repeating_key_xor(&encrypted_plain)?;
 
-    let encrypted_plain = hacker.b64.decode(&encrypted_b64)?;
-    let encrypted_plain = from_utf8(&encrypted_plain)?;
-    let (message, key) = hacker.break_repeating_key_xor(&encrypted_plain)?;
+        assert!(message.contains("Play that funky music, white boy"));
+        assert_eq!(key, "Terminator X: Bring the noise");
+    }
 
-    assert!(message.contains("Play that funky music, white boy"));
-    assert_eq!(key, "Terminator X: Bring the noise");
+    // Without going through strings
+    let hacker = Hacker::new();
+    let encrypted = hacker.b64.decode(&encrypted_b64)?;
+    let (message, key) = hacker.break_repeating_key_xor_bytes(&encrypted, 2..=64, 5)?;
+    assert!(from_utf8(&message)?.contains("Play that funky music, white boy"));
+    assert_eq!(key, b"Terminator X: Bring the noise");
 
     Ok(())
 }
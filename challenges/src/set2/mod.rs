@@ -1,10 +1,12 @@
 use crypt::aes;
+use crypt::attack::{self, OracleMode, OracleProfile};
 use crypt::encoding::{base64, Decoder};
-use crypt::gen;
-use crypt::pad::{pkcs7, pkcs7_validate};
+use crypt::gen::KeySource;
+use crypt::pad::{pkcs7, pkcs7_unpad, pkcs7_validate};
 use crypt::util;
 use crypt::{Error::DataError, Result};
 use std::collections::HashMap;
+use std::ops::Range;
 use std::str::from_utf8;
 
 #[test]
@@ -38,154 +40,86 @@ fn challenge_10() -> Result<()> {
 
 #[test]
 fn challenge_11() {
+    let mut source = crate::key_source();
     for _ in 0..10 {
         // Black-box encryption
         let data_str = String::from("16bytesofpower!!").repeat(6);
         let data_bytes = data_str.as_bytes();
-        let (encrypted, t) = aes::encrypt_oracle(data_bytes).unwrap();
+        let (encrypted, t) = aes::encrypt_oracle_with(data_bytes, &mut source).unwrap();
         let actual_mode = if t { "ECB" } else { "CBC" };
 
         // Detection oracle
         let detected_mode = aes::detection_oracle(data_bytes, &encrypted).unwrap();
         assert_eq!(actual_mode, detected_mode);
     }
-}
 
-struct C12 {
-    unknown: String,
-    key: Vec<u8>,
-    prefix: Option<Vec<u8>>,
-}
-
-impl C12 {
-    /// Returns a new oracle used in challenge 12 & 14.
-    /// `prefix_random` is used by challenge 14 to append
-    /// some random bytes before encrypting.
-    fn new(prefix_random: bool) -> Self {
-        let decoder = base64::Base64::new();
-        let unknown_string = "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK";
-        let unknown = decoder.decode(&unknown_string).unwrap();
-        let unknown = from_utf8(&unknown).unwrap().to_string();
-
-        let prefix = if prefix_random {
-            Some(gen::random_data(3..15))
-        } else {
-            None
+    // Same thing, but only using the oracle as a black box
+    for _ in 0..10 {
+        let oracle = aes::Oracle::builder()
+            .prefix(5..11)
+            .suffix(5..11)
+            .build_with(&mut source)
+            .unwrap();
+        let profile = OracleProfile::detect(|input| oracle.encrypt(input)).unwrap();
+
+        let expected = match oracle.mode() {
+            aes::Mode::ECB => OracleMode::Ecb,
+            _ => OracleMode::Cbc,
         };
-
-        let key = gen::random_key();
-        Self {
-            unknown,
-            key,
-            prefix,
-        }
-    }
-
-    pub fn encrypt(&self, data: &str) -> Result<Vec<u8>> {
-        let mut data_bytes = Vec::new();
-        if let Some(prefix) = &self.prefix {
-            data_bytes.extend_from_slice(prefix);
-        }
-
-        let mut data_string = String::from(data);
-        data_string.push_str(&self.unknown);
-        data_bytes.extend_from_slice(data_string.as_bytes());
-
-        let es = aes::encrypt_128(aes::Mode::ECB, &data_bytes, &self.key)?;
-        Ok(es)
+        assert_eq!(expected, profile.mode);
+        assert_eq!(Some(oracle.prefix().len()), profile.prefix_len);
+        assert_eq!(Some(oracle.suffix().len()), profile.suffix_len);
     }
+}
 
-    pub fn prefix_len(&self) -> usize {
-        self.prefix.as_ref().unwrap().len()
-    }
+/// Returns a new oracle used in challenge 12 & 14, appending the unknown
+/// string to its input. `prefix` is used by challenge 14 to prepend
+/// some random bytes before encrypting.
+fn c12_oracle(prefix: Range<usize>, source: &mut impl KeySource) -> Result<aes::Oracle> {
+    let decoder = base64::Base64::new();
+    let unknown_string = "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK";
+    let unknown = decoder.decode(&unknown_string)?;
+
+    aes::Oracle::builder()
+        .mode(aes::Mode::ECB)
+        .prefix(prefix)
+        .secret(&unknown)
+        .build_with(source)
 }
 
 #[test]
 fn challenge_12() -> Result<()> {
-    let oracle = C12::new(false);
-
-    // 1: detect block size (and length of target bytes)
-    let mut block_size = 0;
-    let mut target_bytes_length = 0;
-    let start_size = oracle.encrypt("")?.len();
-    for n in 1..512 {
-        let encrypted = oracle.encrypt(&"A".repeat(n))?;
-        if encrypted.len() != start_size {
-            block_size = encrypted.len() - start_size;
-            target_bytes_length = start_size - n - 1;
-            break;
-        }
-    }
-
-    assert_eq!(16, block_size);
+    let oracle = c12_oracle(0..0, &mut crate::key_source())?;
 
-    // 2: detect AES mode (using the fact that ECB is deterministic)
-    let input = "a".repeat(block_size * 2);
-    let encrypted = oracle.encrypt(&input)?;
-    let mode = aes::detection_oracle(input.as_bytes(), &encrypted).unwrap();
-    assert_eq!("ECB", mode);
+    // Detect block size and AES mode (using the fact that ECB is deterministic)
+    let profile = OracleProfile::detect(|input| oracle.encrypt(input))?;
+    assert_eq!(16, profile.block_size);
+    assert_eq!(OracleMode::Ecb, profile.mode);
+    assert_eq!(Some(0), profile.prefix_len);
+    assert_eq!(Some(oracle.secret().len()), profile.suffix_len);
 
-    // 3: find the random string, one byte at a time
+    // Find the unknown string, one byte at a time
     // Do like this:
-    //   1. Begin by building a string that has a length of block_size - 1:
+    //   1. Begin by building an input that has a length of block_size - 1:
     //      Lets say that block size is 8, we begin with: "AAAAAAA"
-    //   2. Feed the string to the oracle and save the first block of ciphertext.
+    //   2. Feed the input to the oracle and save the first block of ciphertext.
     //      Before encryption the first block will look like: AAAAAAA?,
     //      i.e the last byte is unknown and the first byte of the target bytes.
-    //   3. Now, constuct a string ending with every possible byte
-    //      and feed that to the oracle, building a list of (input, block) pairs.
-    //   4. Find the input, in your list created in step 3, that
-    //      yields the same first block.
-    //   5. Repeat step 1-4, but with the known byte at last position,
+    //   3. Now, construct an input ending with every possible byte
+    //      and feed that to the oracle until it yields the same first block.
+    //   4. Repeat step 1-3, but with the known byte at last position,
     //      and so on.
     //
     // After a while we have found the first block of the target bytes.
     // By then we will have to keep going with the same principle, but
     // instead looking at the next block of ciphertext.
+    //
+    // The block size and length of the target bytes are found by
+    // feeding longer and longer inputs until the ciphertext grows.
+    let unknown = attack::ecb_byte_at_a_time(|input| oracle.encrypt(input))?;
+    assert_eq!(unknown, oracle.secret());
 
-    let mut target_bytes: Vec<String> = Vec::new();
-    let mut block_index = 0;
-    let mut pad_size = block_size - 1;
-
-    while target_bytes.len() < target_bytes_length {
-        let initial_input = "A".repeat(pad_size);
-        let initial_encrypted = oracle.encrypt(&initial_input)?;
-
-        let window = (block_index * block_size)..(block_index * block_size + block_size);
-        let initial_block = &initial_encrypted[window.clone()];
-
-        let mut table: Vec<(String, Vec<u8>)> = Vec::new();
-
-        for n in 0..256 {
-            let ch = char::from_u32(n).expect("to have valid byte");
-            let input = format!("{}{}{}", initial_input, target_bytes.join(""), ch);
-            let enc = oracle.encrypt(&input)?;
-            let block = &enc[window.clone()];
-            table.push((input, block.to_vec()));
-        }
-
-        let mut found: Option<String> = None;
-        for (input, enc) in &table {
-            if util::slices_equal(&initial_block, enc) {
-                let char = input.chars().last().unwrap();
-                found = Some(char.to_string());
-                break;
-            }
-        }
-
-        let s = found.expect("to found next char");
-        target_bytes.push(s);
-
-        if target_bytes.len() % block_size == 0 {
-            // Found a whole block size worth of bytes from the target bytes.
-            block_index += 1;
-            pad_size = block_size - 1;
-        } else {
-            pad_size -= 1;
-        }
-    }
-
-    let unknown = target_bytes.join("");
+    let unknown = from_utf8(&unknown)?;
     assert!(unknown.contains("Rollin' in my 5.0\nWith my rag-top down so my hair can blow\nThe girlies on standby waving just to say hi\nDid you stop? No, I just drove by"));
     Ok(())
 }
@@ -218,9 +152,9 @@ struct C13 {
 }
 
 impl C13 {
-    fn new() -> Self {
+    fn new(source: &mut impl KeySource) -> Self {
         Self {
-            key: gen::random_key(),
+            key: source.random_key(),
         }
     }
 
@@ -275,7 +209,7 @@ impl C13 {
 #[test]
 fn challenge_13() -> Result<()> {
     let block_size = 16;
-    let c13 = C13::new();
+    let c13 = C13::new(&mut crate::key_source());
 
     // The solution lies in how ECB mode works:
     // Given a sequence of blocks, the plaintext blocks can be directly
@@ -348,84 +282,31 @@ fn challenge_13() -> Result<()> {
 #[test]
 // Byte-at-a-time ECB decryption (Harder)
 fn challenge_14() -> Result<()> {
-    // Same as in #12, but harder: the oracle now append random data before doing the rest
-    let oracle = C12::new(true);
-
-    // Skip step 1 & 2 (refer to #12 for that)
-    let block_size = 16;
-
+    // Same as in #12, but harder: the oracle now prepends random data before doing the rest.
     // We now have: AES-128-ECB(random-prefix || attacker-controlled || target-bytes, random-key)
     // Same goal: decrypt the target-bytes.
     //
     // The hardest part here is to find the prefix length, then
     // we can use the same procedure as in ch. 12.
-
-    // Image feeding the following data to the oracle: "",  "x", "xx", etc.
-    // We would get something like this, before it is encrypted:
-    // [ random-prefix ][ target-bytes ]
-    // [ random-prefix ]x[ target-bytes ]
-    // [ random-prefix ]xx[ target-bytes ]
-    // [ random-prefix ]xxx[ target-bytes ]
-    // [ random-prefix ]xxxx[ target-bytes ]
     //
-    // For each such input and the first block of ciphertext varies,
-    // we have know that the x's are appended in the same block.
-    // If, however, for two inputs of x's, say n and m, know that
-    // n is the amount of padding before a new block was added:
-    // [ P ][ xxx ][ T ... ]
-    // [ P ][ xxx ][ xT ... ]
-
-    let mut padding_len = 0;
-    let a = oracle.encrypt("")?;
-    let mut previous = a.get(0..16).unwrap().to_vec().clone();
-    for n in 1..block_size {
-        let b = oracle.encrypt(&"x".repeat(n))?;
-        let x = &b[0..16];
-        if util::slices_equal(&previous, &x) {
-            padding_len = n - 1;
-            break;
-        }
-        previous = x.to_vec().clone();
-    }
-
-    assert_ne!(padding_len, 0);
-    let prefix_len = block_size - padding_len;
-    assert_eq!(prefix_len, oracle.prefix_len()); // Sanity check
-    let skip_len = prefix_len + padding_len;
-
-    // See ch. 12 for reference.
-    let mut target_bytes: Vec<String> = Vec::new();
-
-    // Should probably keep going, but the principle is the same
-    for _ in 0..block_size {
-        let initial_input = "x".repeat(padding_len + block_size - target_bytes.len() - 1);
-        let initial_encrypted = oracle.encrypt(&initial_input)?;
-        let initial_block = &initial_encrypted[skip_len..skip_len + block_size];
-
-        let mut table: Vec<(String, Vec<u8>)> = Vec::new();
-        for n in 0..256 {
-            let ch = char::from_u32(n).expect("to have valid byte");
-            let input = format!("{}{}{}", initial_input, target_bytes.join(""), ch);
-            let enc = oracle.encrypt(&input)?;
-            let block = &enc[skip_len..skip_len + block_size];
-            table.push((input, block.to_vec()));
-        }
-
-        let mut found: Option<String> = None;
-        for (input, enc) in &table {
-            if util::slices_equal(&initial_block, enc) {
-                let char = input.chars().last().unwrap();
-                found = Some(char.to_string());
-                break;
-            }
-        }
-
-        let s = found.expect("to found next char");
-        target_bytes.push(s);
+    // Imagine feeding two inputs to the oracle that only differ in the first byte,
+    // e.g. "\x00" and "\x01". The first ciphertext block that differs is then
+    // the last block of the prefix:
+    // [ random-prefix ... ][ ...prefix x ][ target-bytes ]
+    //
+    // Adding filler in front of that byte until the block no longer changes
+    // gives the amount of padding n needed to complete the last prefix block:
+    // [ random-prefix ... ][ ...prefix fff ][ x target-bytes ]
+    //
+    // The input then always starts with n bytes of filler, after which
+    // the rest of the blocks are aligned as in #12.
+    let mut source = crate::key_source();
+    for prefix in [3..15, 17..60] {
+        let oracle = c12_oracle(prefix, &mut source)?;
+        let unknown = attack::ecb_byte_at_a_time(|input| oracle.encrypt(input))?;
+        assert_eq!(unknown, oracle.secret());
+        assert!(from_utf8(&unknown)?.starts_with("Rollin'"));
     }
-
-    let unknown = target_bytes.join("");
-    assert!(unknown.contains("Rollin'"));
     Ok(())
 }
 
@@ -435,4 +316,74 @@ fn challenge_15() {
     assert!(res.is_ok());
     let res = pkcs7_validate("test\x04\x04\x04", 8);
     assert!(res.is_err());
+
+    let res = pkcs7_unpad(b"ICE ICE BABY\x04\x04\x04\x04", 16);
+    assert_eq!(res.unwrap(), b"ICE ICE BABY");
+    let res = pkcs7_unpad(b"ICE ICE BABY\x05\x05\x05\x05", 16);
+    assert!(res.is_err());
+    let res = pkcs7_unpad(b"ICE ICE BABY\x01\x02\x03\x04", 16);
+    assert!(res.is_err());
+}
+
+// Challenge 16
+
+struct C16 {
+    key: Vec<u8>,
+    iv: Vec<u8>,
+}
+
+impl C16 {
+    const PREFIX: &'static str = "comment1=cooking%20MCs;userdata=";
+    const SUFFIX: &'static str = ";comment2=%20like%20a%20pound%20of%20bacon";
+
+    fn new(source: &mut impl KeySource) -> Self {
+        Self {
+            key: source.random_key(),
+            iv: source.random_key(),
+        }
+    }
+
+    /// Quotes out ";" and "=" from the user data before encrypting it.
+    fn encrypt(&self, userdata: &str) -> Result<Vec<u8>> {
+        let userdata = userdata.replace(";", "%3B").replace("=", "%3D");
+        let data = format!("{}{}{}", Self::PREFIX, userdata, Self::SUFFIX);
+        aes::encrypt_128(aes::Mode::CBC(self.iv.clone()), data.as_bytes(), &self.key)
+    }
+
+    fn is_admin(&self, iv: &[u8], encrypted: &[u8]) -> Result<bool> {
+        let decrypted = aes::decrypt_128(aes::Mode::CBC(iv.to_vec()), encrypted, &self.key)?;
+        // The flipped block decrypts to garbage, which isn't necessarily UTF-8
+        let s = String::from_utf8_lossy(&decrypted);
+        Ok(s.split(';').any(|field| field == "admin=true"))
+    }
+}
+
+#[test]
+fn challenge_16() -> Result<()> {
+    let c16 = C16::new(&mut crate::key_source());
+
+    // The oracle quotes the characters we need
+    let encrypted = c16.encrypt(";admin=true;")?;
+    assert!(!c16.is_admin(&c16.iv, &encrypted)?);
+
+    // In CBC each plaintext block is XOR:ed with the previous ciphertext block
+    // after decryption, so flipping a bit in ciphertext block n flips the same
+    // bit in plaintext block n + 1 (and scrambles block n):
+    //   [comment1=cooking][%20MCs;userdata=][XadminXtrueX;com]...
+    //                             ^ flip              ^ result
+    // The prefix is exactly two blocks, so our data starts at block 2.
+    let userdata = "XadminXtrueX";
+    let mut encrypted = c16.encrypt(userdata)?;
+    let mut iv = c16.iv.clone();
+    let offset = C16::PREFIX.len();
+    attack::bitflip::cbc_flip(
+        &mut iv,
+        &mut encrypted,
+        offset,
+        userdata.as_bytes(),
+        b";admin=true;",
+    )?;
+
+    assert!(c16.is_admin(&iv, &encrypted)?);
+    Ok(())
 }
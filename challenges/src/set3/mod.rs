@@ -0,0 +1,218 @@
+use crypt::aes;
+use crypt::attack::{keystream, padding_oracle, prng};
+use crypt::encoding::{base64, Decoder};
+use crypt::gen::{mt19937, KeySource, Mt19937, Mt19937_64};
+use crypt::{Hacker, Result};
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use std::str::from_utf8;
+
+/// The server side of challenge 17: encrypts one of the strings under a
+/// random key and tells whether a ciphertext has valid padding.
+struct C17 {
+    key: Vec<u8>,
+}
+
+impl C17 {
+    fn new(source: &mut impl KeySource) -> Self {
+        Self {
+            key: source.random_key(),
+        }
+    }
+
+    /// Encrypts one of the strings at random with a random IV,
+    /// returning the ciphertext and the IV.
+    fn encrypt(
+        &self,
+        strings: &[Vec<u8>],
+        source: &mut impl KeySource,
+    ) -> Result<(Vec<u8>, Vec<u8>)> {
+        let plaintext = strings.choose(source).unwrap();
+        let iv = source.random_key();
+        let ciphertext = aes::encrypt_128(aes::Mode::CBC(iv.clone()), plaintext, &self.key)?;
+        Ok((ciphertext, iv))
+    }
+
+    fn padding_valid(&self, ciphertext: &[u8], iv: &[u8]) -> bool {
+        aes::decrypt_128(aes::Mode::CBC(iv.to_vec()), ciphertext, &self.key).is_ok()
+    }
+}
+
+#[test]
+fn challenge_17() -> Result<()> {
+    let decoder = base64::Base64::new();
+    let strings = include_str!("../../data/set3_challenge17.txt")
+        .lines()
+        .map(|line| decoder.decode(line))
+        .collect::<Result<Vec<Vec<u8>>>>()?;
+
+    let mut source = crate::key_source();
+    let server = C17::new(&mut source);
+    for _ in 0..5 {
+        let (ciphertext, iv) = server.encrypt(&strings, &mut source)?;
+        let oracle = |c: &[u8], iv: &[u8]| server.padding_valid(c, iv);
+
+        let plaintext = padding_oracle::decrypt(&ciphertext, &iv, oracle)?;
+        assert!(strings.contains(&plaintext));
+        assert!(from_utf8(&plaintext)?.starts_with("00000"));
+    }
+
+    // CBC-R: the same oracle can be used to encrypt a message of our own,
+    // including block aligned ones that get a whole block of padding.
+    let messages: [&[u8]; 4] = [
+        b"Rollin' in my 5.0",
+        b"",
+        b"sixteen bytes!!!",
+        b"thirty-two bytes of plaintext!!!",
+    ];
+    for message in messages {
+        let (iv, ciphertext) = padding_oracle::forge(message, 16, |c: &[u8], iv: &[u8]| {
+            server.padding_valid(c, iv)
+        })?;
+        assert!(server.padding_valid(&ciphertext, &iv));
+        let decrypted = aes::decrypt_128(aes::Mode::CBC(iv), &ciphertext, &server.key)?;
+        assert_eq!(decrypted, message);
+    }
+    Ok(())
+}
+
+/// Encrypts every string with CTR under the same key and nonce.
+fn encrypt_fixed_nonce(strings: &[Vec<u8>], source: &mut impl KeySource) -> Result<Vec<Vec<u8>>> {
+    let key = source.random_key();
+    let mode = aes::Mode::CTR(vec![0; 8], aes::Counter::LittleEndian64);
+    strings
+        .iter()
+        .map(|s| aes::encrypt_128(mode.clone(), s, &key))
+        .collect()
+}
+
+/// Counts the bytes of `keystream` that decrypt `ciphertexts` to `strings`.
+fn count_correct(keystream: &[u8], ciphertexts: &[Vec<u8>], strings: &[Vec<u8>]) -> usize {
+    (0..keystream.len())
+        .filter(|i| {
+            ciphertexts
+                .iter()
+                .zip(strings)
+                .filter_map(|(c, s)| Some(c.get(*i)? ^ s[*i]))
+                .all(|k| k == keystream[*i])
+        })
+        .count()
+}
+
+#[test]
+fn challenge_19() -> Result<()> {
+    let decoder = base64::Base64::new();
+    let strings = include_str!("../../data/set3_challenge19.txt")
+        .lines()
+        .map(|line| decoder.decode(line))
+        .collect::<Result<Vec<Vec<u8>>>>()?;
+    let ciphertexts = encrypt_fixed_nonce(&strings, &mut crate::key_source())?;
+
+    let mut keystream = keystream::recover(&Hacker::new(), &ciphertexts);
+    assert_eq!(keystream.len(), 38);
+    // Every line is at least 20 bytes long, so the start of the keystream
+    // has plenty of samples. The first column is mostly wrong in case only.
+    assert!(count_correct(&keystream[..20], &ciphertexts, &strings) >= 15);
+
+    // The end only has a couple of samples per column, so fix it with a
+    // guess of the longest line.
+    let longest = ciphertexts.iter().position(|c| c.len() == 38).unwrap();
+    keystream::correct(
+        &mut keystream,
+        &ciphertexts[longest],
+        0,
+        b"He, too, has been changed in his turn,",
+    )?;
+    for (c, s) in ciphertexts.iter().zip(&strings) {
+        assert_eq!(&keystream::decrypt(&keystream, c), s);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn challenge_20() -> Result<()> {
+    let decoder = base64::Base64::new();
+    let strings = include_str!("../../data/set3_challenge20.txt")
+        .lines()
+        .map(|line| decoder.decode(line))
+        .collect::<Result<Vec<Vec<u8>>>>()?;
+    let ciphertexts = encrypt_fixed_nonce(&strings, &mut crate::key_source())?;
+
+    // Truncated to the same length, the ciphertexts are repeating-key XOR
+    // with the keystream as the key.
+    let len = ciphertexts.iter().map(|c| c.len()).min().unwrap();
+    let truncated: Vec<&[u8]> = ciphertexts.iter().map(|c| &c[..len]).collect();
+    let mut keystream = keystream::recover(&Hacker::new(), &truncated);
+    assert_eq!(keystream.len(), len);
+
+    // The first letters of the lines don't look like English, but
+    // everything after them is right.
+    assert!(count_correct(&keystream, &ciphertexts, &strings) >= len - 1);
+    keystream::correct(&mut keystream, truncated[0], 0, b"I")?;
+    for (c, s) in truncated.iter().zip(&strings) {
+        assert_eq!(keystream::decrypt(&keystream, c), s[..len]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn challenge_21() {
+    let mut rng = Mt19937::new(5489);
+    let outputs: Vec<u32> = (0..3).map(|_| rng.next_u32()).collect();
+    assert_eq!(outputs, [3499211612, 581869302, 3890346734]);
+
+    let mut rng = Mt19937_64::new(5489);
+    assert_eq!(rng.next_u64(), 14514284786278117030);
+}
+
+#[test]
+fn challenge_22() {
+    // Instead of actually waiting, the clock is simulated.
+    let mut rng = crate::key_source();
+    let seeded_at = prng::unix_time() + rng.gen_range(40..1000);
+    let output = Mt19937::new(seeded_at as u32).next_u32();
+    let now = seeded_at + rng.gen_range(40..1000);
+
+    assert_eq!(
+        prng::recover_time_seed(&[output], now, 2000),
+        Some(seeded_at as u32)
+    );
+}
+
+#[test]
+fn challenge_23() -> Result<()> {
+    let mut rng = Mt19937::new(crate::key_source().gen());
+    let outputs: Vec<u32> = (0..Mt19937::STATE_LEN).map(|_| rng.next_u32()).collect();
+
+    let mut clone = Mt19937::from_outputs(&outputs)?;
+    for _ in 0..1000 {
+        assert_eq!(clone.next_u32(), rng.next_u32());
+    }
+    Ok(())
+}
+
+#[test]
+fn challenge_24() {
+    let mut rng = crate::key_source();
+    let seed: u16 = rng.gen();
+    let mut plaintext = rng.random_data(5..20);
+    plaintext.extend_from_slice(&[b'A'; 14]);
+    let ciphertext = mt19937::xor_keystream(seed as u32, &plaintext);
+
+    let offset = ciphertext.len() - 14;
+    assert_eq!(
+        prng::break_stream_cipher(&ciphertext, offset, &[b'A'; 14]),
+        Some(seed)
+    );
+
+    // A password reset token made from MT19937 seeded with the current time
+    let now = prng::unix_time();
+    let mut token = [0u8; 16];
+    Mt19937::new(now as u32).fill_bytes(&mut token);
+    assert!(prng::is_time_seeded_token(&token, now + 5, 3600));
+
+    rng.fill_bytes(&mut token);
+    assert!(!prng::is_time_seeded_token(&token, now + 5, 3600));
+}
@@ -0,0 +1,76 @@
+use crypt::attack::length_extension;
+use crypt::gen::KeySource;
+use crypt::hash::{Md4, Sha1};
+use crypt::Result;
+
+/// Secret-prefix MAC: SHA-1(key || message).
+fn sha1_mac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut sha1 = Sha1::new();
+    sha1.update(key);
+    sha1.update(message);
+    sha1.finalize()
+}
+
+#[test]
+fn challenge_28() {
+    let key = crate::key_source().random_key();
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let mac = sha1_mac(&key, message);
+
+    let mut joined = key.clone();
+    joined.extend_from_slice(message);
+    assert_eq!(mac, Sha1::digest(&joined));
+
+    // The message can't be changed without the MAC changing
+    let mut tampered = message.to_vec();
+    tampered[40] ^= 1;
+    assert_ne!(sha1_mac(&key, &tampered), mac);
+
+    // And a MAC can't be made without the key
+    assert_ne!(Sha1::digest(message), mac);
+}
+
+#[test]
+fn challenge_29() -> Result<()> {
+    let mut source = crate::key_source();
+    let key = source.random_data(1..64);
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let mac = sha1_mac(&key, message);
+    let verify = |message: &[u8], mac: &[u8]| sha1_mac(&key, message) == mac;
+
+    // The key length isn't known, so try them all until the server accepts one.
+    let forgery = length_extension::forge::<Sha1>(&mac, message, 0..=64, b";admin=true")
+        .find(|f| f.as_ref().map_or(true, |f| verify(&f.message, &f.mac)))
+        .expect("no forgery accepted")?;
+
+    assert_eq!(forgery.secret_len, key.len());
+    assert!(forgery.message.ends_with(b";admin=true"));
+    Ok(())
+}
+
+#[test]
+fn challenge_30() -> Result<()> {
+    let mut source = crate::key_source();
+    let key = source.random_data(1..64);
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let mac = {
+        let mut md4 = Md4::new();
+        md4.update(&key);
+        md4.update(message);
+        md4.finalize()
+    };
+    let verify = |message: &[u8], mac: &[u8]| {
+        let mut joined = key.clone();
+        joined.extend_from_slice(message);
+        Md4::digest(&joined) == mac
+    };
+
+    // Same as #29, the attack only depends on the padding of the hash.
+    let forgery = length_extension::forge::<Md4>(&mac, message, 0..=64, b";admin=true")
+        .find(|f| f.as_ref().map_or(true, |f| verify(&f.message, &f.mac)))
+        .expect("no forgery accepted")?;
+
+    assert_eq!(forgery.secret_len, key.len());
+    assert!(forgery.message.ends_with(b";admin=true"));
+    Ok(())
+}
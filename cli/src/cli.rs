@@ -1,13 +1,28 @@
 use clap::{app_from_crate, App, AppSettings, Arg, ArgMatches};
-use crypt::aes::{decrypt_128, encrypt_128, Mode};
+use crypt::aes::{decrypt_stream, encrypt_stream, gcm, Mode};
+use crypt::attack::{keystream, length_extension};
 use crypt::encoding::{base64::Base64, hex::Hex, Decoder, Encoder};
+use crypt::hash::{Md4, MerkleDamgard, Sha1};
+use crypt::pad::Padding;
 use crypt::util;
 use crypt::{Error, Hacker, Result};
 use openssl::hash::{self, MessageDigest};
+use std::io::{self, BufWriter, Read, Write};
 use std::str::from_utf8;
 
 const IN_ARG_NAME: &str = "in";
 const OUT_ARG_NAME: &str = "out";
+const METHODS: [&str; 9] = [
+    "repeating-key-xor",
+    "rkx",
+    "aes-ecb",
+    "aes-cbc",
+    "aes-pcbc",
+    "aes-cfb8",
+    "aes-cfb",
+    "aes-ofb",
+    "aes-gcm",
+];
 
 pub struct Cli {
     hex: Hex,
@@ -36,7 +51,7 @@ impl Cli {
                     .about("Encrypt into a ciphertext.")
                     .arg(
                         Arg::new("key")
-                            .help("Hex encoded string to use as key.")
+                            .help("Hex encoded string to use as key. For AES the key length (16, 24 or 32 bytes) selects AES-128, AES-192 or AES-256.")
                             .long("key")
                             .short('k')
                             .required(true)
@@ -60,12 +75,25 @@ impl Cli {
                             .long("iv")
                             .takes_value(true),
                     )
+                    .arg(
+                        Arg::new("aad")
+                            .help("Hex encoded additional authenticated data, used by aes-gcm.")
+                            .long("aad")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::new("padding")
+                            .help("Padding used by the block modes aes-ecb, aes-cbc and aes-pcbc.")
+                            .long("padding")
+                            .possible_values(["pkcs7", "zero", "iso7816", "x923", "none"])
+                            .default_value("pkcs7"),
+                    )
                     .arg(
                         Arg::new("method")
                             .help("Encryption method to use.")
                             .long("method")
                             .short('m')
-                            .possible_values(["repeating-key-xor", "rkx", "aes-cbc", "aes-ecb"])
+                            .possible_values(METHODS)
                             .required(true),
                     ),
             )
@@ -74,7 +102,7 @@ impl Cli {
                     .about("Decrypt a ciphertext.")
                     .arg(
                         Arg::new("key")
-                            .help("Hex encoded string to use as key.")
+                            .help("Hex encoded string to use as key. For AES the key length (16, 24 or 32 bytes) selects AES-128, AES-192 or AES-256.")
                             .long("key")
                             .short('k')
                             .required(true)
@@ -98,12 +126,25 @@ impl Cli {
                             .long("iv")
                             .takes_value(true),
                     )
+                    .arg(
+                        Arg::new("aad")
+                            .help("Hex encoded additional authenticated data, used by aes-gcm.")
+                            .long("aad")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::new("padding")
+                            .help("Padding used by the block modes aes-ecb, aes-cbc and aes-pcbc.")
+                            .long("padding")
+                            .possible_values(["pkcs7", "zero", "iso7816", "x923", "none"])
+                            .default_value("pkcs7"),
+                    )
                     .arg(
                         Arg::new("method")
                             .help("Decryption method to use.")
                             .long("method")
                             .short('m')
-                            .possible_values(["repeating-key-xor", "rkx", "aes-cbc", "aes-ecb"])
+                            .possible_values(METHODS)
                             .required(true),
                     ),
             )
@@ -153,6 +194,120 @@ impl Cli {
                             .required(true),
                     ),
             )
+            .subcommand(
+                App::new("crack")
+                    .about("Break ciphers without knowing the key.")
+                    .setting(AppSettings::SubcommandRequiredElseHelp)
+                    .subcommand(
+                        App::new("xor-single")
+                            .about("Break single-byte XOR, printing the best keys and plaintexts.")
+                            .args(crack_args())
+                            .arg(top_arg())
+                            .arg(
+                                Arg::new("lines")
+                                    .help("Treat each line of input as a separate ciphertext and find those encrypted with single-byte XOR.")
+                                    .long("lines"),
+                            ),
+                    )
+                    .subcommand(
+                        App::new("xor-repeating")
+                            .about("Break repeating-key XOR, printing the best keys and plaintexts.")
+                            .args(crack_args())
+                            .arg(top_arg())
+                            .arg(
+                                Arg::new("key-sizes")
+                                    .help("Number of the most likely key sizes to try.")
+                                    .long("key-sizes")
+                                    .default_value("5"),
+                            )
+                            .arg(
+                                Arg::new("min-key-size")
+                                    .help("Smallest key size to try.")
+                                    .long("min-key-size")
+                                    .default_value("2"),
+                            )
+                            .arg(
+                                Arg::new("max-key-size")
+                                    .help("Largest key size to try.")
+                                    .long("max-key-size")
+                                    .default_value("40"),
+                            ),
+                    )
+                    .subcommand(
+                        App::new("keystream-reuse")
+                            .about("Recover a keystream used for several ciphertexts, one per line of input, printing the keystream and plaintexts.")
+                            .args(crack_args())
+                            // Raw ciphertexts may contain newlines, so they
+                            // can't be given one per line.
+                            .mut_arg("input-encoding", |arg| arg.default_value("b64"))
+                            .arg(
+                                Arg::new("correct")
+                                    .help("Correct the keystream with known plaintext, given as LINE:OFFSET:TEXT.")
+                                    .long("correct")
+                                    .short('c')
+                                    .takes_value(true)
+                                    .multiple_occurrences(true),
+                            ),
+                    ),
+            )
+            .subcommand(
+                App::new("forge")
+                    .about("Forge messages without knowing the key.")
+                    .setting(AppSettings::SubcommandRequiredElseHelp)
+                    .subcommand(
+                        App::new("length-extension")
+                            .about("Extend a message authenticated with hash(secret || message), printing a forged message and MAC for every secret length.")
+                            .arg(
+                                Arg::new("digest")
+                                    .long("digest")
+                                    .short('d')
+                                    .help("Message digest kind.")
+                                    .required(true)
+                                    .possible_values(["md4", "sha1"]),
+                            )
+                            .arg(
+                                Arg::new(IN_ARG_NAME)
+                                    .long(IN_ARG_NAME)
+                                    .help("Read the message from file, else use stdin.")
+                                    .takes_value(true),
+                            )
+                            .arg(
+                                Arg::new("mac")
+                                    .help("MAC of the message in hex.")
+                                    .long("mac")
+                                    .short('m')
+                                    .takes_value(true)
+                                    .required(true),
+                            )
+                            .arg(
+                                Arg::new("extension")
+                                    .help("Data to append to the message.")
+                                    .long("extension")
+                                    .short('x')
+                                    .takes_value(true)
+                                    .required_unless_present("extension-hex")
+                                    .conflicts_with("extension-hex"),
+                            )
+                            .arg(
+                                Arg::new("extension-hex")
+                                    .help("Data to append to the message in hex.")
+                                    .long("extension-hex")
+                                    .takes_value(true),
+                            )
+                            .arg(
+                                Arg::new("min-secret-len")
+                                    .help("Shortest secret length to try.")
+                                    .long("min-secret-len")
+                                    .default_value("0"),
+                            )
+                            .arg(
+                                Arg::new("max-secret-len")
+                                    .help("Longest secret length to try.")
+                                    .long("max-secret-len")
+                                    .default_value("64"),
+                            ),
+                    ),
+            )
             .subcommand(
                 App::new("hash")
                     .about("Hash functions.")
@@ -162,7 +317,7 @@ impl Cli {
                             .short('d')
                             .help("Message digest kind.")
                             .required(true)
-                            .possible_values(&["md5", "sha256", "sha512", "sha3_256", "shake_256"]),
+                            .possible_values(&["md4", "md5", "sha1", "sha256", "sha512", "sha3_256", "shake_256"]),
                     )
                     .arg(
                         Arg::new(IN_ARG_NAME)
@@ -184,6 +339,8 @@ impl Cli {
             Some(("decrypt", sub_matches)) => self.handle_decrypt(sub_matches),
             Some(("encode", sub_matches)) => self.handle_encode(sub_matches),
             Some(("decode", sub_matches)) => self.handle_decode(sub_matches),
+            Some(("crack", sub_matches)) => self.handle_crack(sub_matches),
+            Some(("forge", sub_matches)) => self.handle_forge(sub_matches),
             Some(("hash", sub_matches)) => self.handle_hash(sub_matches),
             _ => unreachable!(),
         }
@@ -193,16 +350,31 @@ impl Cli {
 // encrypt/decrypt
 impl Cli {
     fn handle_encrypt(&self, matches: &ArgMatches) -> Result<()> {
-        let buffer = get_input(matches.value_of(IN_ARG_NAME))?;
         let key_hex = matches.value_of("key").unwrap().trim();
         let key = self.hex.decode(key_hex)?;
 
-        let encrypted = match matches.value_of("method").unwrap() {
+        let method = matches.value_of("method").unwrap();
+        if method.starts_with("aes-") && method != "aes-gcm" {
+            // Block cipher modes are streamed so that large inputs don't
+            // have to fit in memory.
+            let mode = self.aes_mode(method, matches)?;
+            let reader = open_input(matches.value_of(IN_ARG_NAME))?;
+            let writer = open_output(matches.value_of(OUT_ARG_NAME))?;
+            let padding = get_padding(matches.value_of("padding").unwrap());
+            encrypt_stream(mode, padding, reader, writer, &key)?;
+            return Ok(());
+        }
+
+        let buffer = get_input(matches.value_of(IN_ARG_NAME))?;
+        let encrypted = match method {
             "rkx" | "repeating-key-xor" => self.hacker.repeating_key_xor(&buffer, &key)?,
-            "aes-ecb" => encrypt_128(Mode::ECB, &buffer, &key)?,
-            "aes-cbc" => {
-                let iv = self.get_iv(matches.value_of("iv"))?;
-                encrypt_128(Mode::CBC(iv), &buffer, &key)?
+            "aes-gcm" => {
+                // The tag is appended to the ciphertext.
+                let nonce = self.get_iv("aes-gcm", matches.value_of("iv"))?;
+                let aad = self.get_aad(matches.value_of("aad"))?;
+                let (mut encrypted, tag) = gcm::encrypt(&key, &nonce, &aad, &buffer)?;
+                encrypted.extend_from_slice(&tag);
+                encrypted
             }
             _ => unreachable!(),
         };
@@ -211,16 +383,34 @@ impl Cli {
     }
 
     fn handle_decrypt(&self, matches: &ArgMatches) -> Result<()> {
-        let buffer = get_input(matches.value_of(IN_ARG_NAME))?;
         let key_hex = matches.value_of("key").unwrap().trim();
         let key = self.hex.decode(key_hex)?;
 
-        let decrypted = match matches.value_of("method").unwrap() {
+        let method = matches.value_of("method").unwrap();
+        if method.starts_with("aes-") && method != "aes-gcm" {
+            // Block cipher modes are streamed so that large inputs don't
+            // have to fit in memory.
+            let mode = self.aes_mode(method, matches)?;
+            let reader = open_input(matches.value_of(IN_ARG_NAME))?;
+            let writer = open_output(matches.value_of(OUT_ARG_NAME))?;
+            let padding = get_padding(matches.value_of("padding").unwrap());
+            decrypt_stream(mode, padding, reader, writer, &key)?;
+            return Ok(());
+        }
+
+        let buffer = get_input(matches.value_of(IN_ARG_NAME))?;
+        let decrypted = match method {
             "rkx" | "repeating-key-xor" => self.hacker.repeating_key_xor(&buffer, &key)?,
-            "aes-ecb" => decrypt_128(Mode::ECB, &buffer, &key)?,
-            "aes-cbc" => {
-                let iv = self.get_iv(matches.value_of("iv"))?;
-                decrypt_128(Mode::CBC(iv), &buffer, &key)?
+            "aes-gcm" => {
+                if buffer.len() < gcm::TAG_SIZE {
+                    return Err(Error::DataError(
+                        "input too short to contain a GCM tag".to_string(),
+                    ));
+                }
+                let nonce = self.get_iv("aes-gcm", matches.value_of("iv"))?;
+                let aad = self.get_aad(matches.value_of("aad"))?;
+                let (encrypted, tag) = buffer.split_at(buffer.len() - gcm::TAG_SIZE);
+                gcm::decrypt(&key, &nonce, &aad, encrypted, tag)?
             }
             _ => unreachable!(),
         };
@@ -260,34 +450,305 @@ impl Cli {
         write_output(matches.value_of(OUT_ARG_NAME), &v)
     }
 
-    fn get_iv(&self, iv: Option<&str>) -> Result<Vec<u8>> {
+    fn aes_mode(&self, method: &str, matches: &ArgMatches) -> Result<Mode> {
+        if method == "aes-ecb" {
+            return Ok(Mode::ECB);
+        }
+
+        let iv = self.get_iv(method, matches.value_of("iv"))?;
+        let mode = match method {
+            "aes-cbc" => Mode::CBC(iv),
+            "aes-pcbc" => Mode::PCBC(iv),
+            "aes-cfb8" => Mode::CFB8(iv),
+            "aes-cfb" => Mode::CFB128(iv),
+            "aes-ofb" => Mode::OFB(iv),
+            _ => unreachable!(),
+        };
+        Ok(mode)
+    }
+
+    fn get_aad(&self, aad: Option<&str>) -> Result<Vec<u8>> {
+        match aad {
+            Some(s) => self.hex.decode(s),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn get_iv(&self, method: &str, iv: Option<&str>) -> Result<Vec<u8>> {
         match iv {
             Some(s) => self.hex.decode(s),
-            None => Err(Error::ArgError(
-                "CBC mode requires the --iv option".to_string(),
-            )),
+            None => Err(Error::ArgError(format!(
+                "{} requires the --iv option",
+                method
+            ))),
         }
     }
 }
 
+// crack
 impl Cli {
-    fn handle_hash(&self, matches: &ArgMatches) -> Result<()> {
+    fn handle_crack(&self, matches: &ArgMatches) -> Result<()> {
+        let (name, matches) = matches.subcommand().unwrap();
+        let encoding = matches.value_of("input-encoding").unwrap();
         let buffer = get_input(matches.value_of(IN_ARG_NAME))?;
-        let digest = match matches.value_of("digest").unwrap() {
-            "md5" => MessageDigest::md5(),
-            "sha256" => MessageDigest::sha256(),
-            "sha512" => MessageDigest::sha512(),
-            "sha3_256" => MessageDigest::sha3_256(),
-            "shake_256" => MessageDigest::shake_256(),
+
+        let best = match name {
+            "xor-single" if matches.is_present("lines") => {
+                let lines = from_utf8(&buffer)?
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| self.decode_input(encoding, line.as_bytes()))
+                    .collect::<Result<Vec<Vec<u8>>>>()?;
+
+                let top = get_number(matches, "top")?;
+                let found = self.hacker.find_single_byte_xor(&lines, top);
+                for (rank, (line, c)) in found.iter().enumerate() {
+                    print_candidate(rank, &[c.key], c.score, &c.plaintext, Some(line + 1));
+                }
+                found.into_iter().next().map(|(_, c)| c.plaintext)
+            }
+            "xor-single" => {
+                let data = self.decode_input(encoding, &buffer)?;
+                let top = get_number(matches, "top")?;
+                let found = self.hacker.crack_single_byte_xor(&data, top);
+                for (rank, c) in found.iter().enumerate() {
+                    print_candidate(rank, &[c.key], c.score, &c.plaintext, None);
+                }
+                found.into_iter().next().map(|c| c.plaintext)
+            }
+            "xor-repeating" => {
+                let data = self.decode_input(encoding, &buffer)?;
+                let min = get_number(matches, "min-key-size")?;
+                let max = get_number(matches, "max-key-size")?;
+                let key_sizes = get_number(matches, "key-sizes")?;
+                let top = get_number(matches, "top")?;
+                let found = self
+                    .hacker
+                    .crack_repeating_key_xor(&data, min..=max, key_sizes);
+                for (rank, c) in found.iter().take(top).enumerate() {
+                    print_candidate(rank, &c.key, c.score, &c.plaintext, None);
+                }
+                found.into_iter().next().map(|c| c.plaintext)
+            }
+            "keystream-reuse" => {
+                if encoding == "raw" {
+                    return Err(Error::ArgError(
+                        "keystream-reuse needs one encoded ciphertext per line, use hex or b64"
+                            .to_string(),
+                    ));
+                }
+                let ciphertexts = buffer
+                    .split(|b| *b == b'\n')
+                    .filter(|line| !line.trim_ascii().is_empty())
+                    .map(|line| self.decode_input(encoding, line))
+                    .collect::<Result<Vec<Vec<u8>>>>()?;
+
+                let mut ks = keystream::recover(&self.hacker, &ciphertexts);
+                for correction in matches.values_of("correct").into_iter().flatten() {
+                    let (line, offset, text) = parse_correction(correction)?;
+                    let ciphertext = ciphertexts
+                        .get(line.wrapping_sub(1))
+                        .ok_or_else(|| Error::ArgError(format!("no line {} in the input", line)))?;
+                    keystream::correct(&mut ks, ciphertext, offset, text.as_bytes())?;
+                }
+
+                println!("keystream {}", self.hex.encode(&ks)?);
+                let plaintexts: Vec<Vec<u8>> = ciphertexts
+                    .iter()
+                    .map(|c| keystream::decrypt(&ks, c))
+                    .collect();
+                for (line, plaintext) in plaintexts.iter().enumerate() {
+                    println!("{:>4}: \"{}\"", line + 1, preview(plaintext));
+                }
+                Some(plaintexts.join(&b'\n'))
+            }
+            _ => unreachable!(),
+        };
+
+        match (best, matches.value_of(OUT_ARG_NAME)) {
+            (Some(plaintext), Some(outfile)) => util::write_bytes(outfile, &plaintext),
+            (None, _) => Err(Error::DataError("no candidates found".to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    fn decode_input(&self, encoding: &str, buffer: &[u8]) -> Result<Vec<u8>> {
+        match encoding {
+            "raw" => Ok(buffer.to_vec()),
+            // Encoded input may be split over several lines.
+            "hex" => self.hex.decode(&util::into_line(from_utf8(buffer)?)),
+            "b64" | "base64" => self.b64.decode(&util::into_line(from_utf8(buffer)?)),
             _ => unreachable!(),
+        }
+    }
+}
+
+// forge
+impl Cli {
+    fn handle_forge(&self, matches: &ArgMatches) -> Result<()> {
+        let (name, matches) = matches.subcommand().unwrap();
+        match name {
+            "length-extension" => match matches.value_of("digest").unwrap() {
+                "md4" => self.forge_length_extension::<Md4>(matches),
+                "sha1" => self.forge_length_extension::<Sha1>(matches),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn forge_length_extension<H: MerkleDamgard>(&self, matches: &ArgMatches) -> Result<()> {
+        let message = get_input(matches.value_of(IN_ARG_NAME))?;
+        let mac = self.hex.decode(matches.value_of("mac").unwrap())?;
+        let extension = match matches.value_of("extension-hex") {
+            Some(hex) => self.hex.decode(hex)?,
+            None => matches.value_of("extension").unwrap().as_bytes().to_vec(),
+        };
+        let min = get_number(matches, "min-secret-len")?;
+        let max = get_number(matches, "max-secret-len")?;
+
+        for f in length_extension::forge::<H>(&mac, &message, min..=max, &extension) {
+            let f = f?;
+            println!(
+                "secret length {:>3}: mac {} message {}",
+                f.secret_len,
+                self.hex.encode(&f.mac)?,
+                self.hex.encode(&f.message)?
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Cli {
+    fn handle_hash(&self, matches: &ArgMatches) -> Result<()> {
+        let buffer = get_input(matches.value_of(IN_ARG_NAME))?;
+        let res = match matches.value_of("digest").unwrap() {
+            "md4" => Md4::digest(&buffer),
+            "sha1" => Sha1::digest(&buffer),
+            name => {
+                let digest = match name {
+                    "md5" => MessageDigest::md5(),
+                    "sha256" => MessageDigest::sha256(),
+                    "sha512" => MessageDigest::sha512(),
+                    "sha3_256" => MessageDigest::sha3_256(),
+                    "shake_256" => MessageDigest::shake_256(),
+                    _ => unreachable!(),
+                };
+                hash::hash(digest, &buffer)?.to_vec()
+            }
         };
 
-        let res = hash::hash(digest, &buffer)?;
         let hex = self.hex.encode(&res)?;
         write_output(matches.value_of(OUT_ARG_NAME), hex.as_bytes())
     }
 }
 
+/// Arguments shared by the crack subcommands.
+fn crack_args<'a>() -> [Arg<'a>; 3] {
+    [
+        Arg::new(IN_ARG_NAME)
+            .long(IN_ARG_NAME)
+            .help("Read input from file, else use stdin.")
+            .takes_value(true),
+        Arg::new(OUT_ARG_NAME)
+            .long(OUT_ARG_NAME)
+            .help("Write the best plaintext to file.")
+            .takes_value(true),
+        Arg::new("input-encoding")
+            .help("Encoding of the ciphertext.")
+            .long("input-encoding")
+            .short('e')
+            .possible_values(["hex", "b64", "base64", "raw"])
+            .default_value("raw"),
+    ]
+}
+
+fn top_arg<'a>() -> Arg<'a> {
+    Arg::new("top")
+        .help("Number of candidates to print.")
+        .long("top")
+        .short('n')
+        .default_value("5")
+}
+
+fn get_number(matches: &ArgMatches, name: &str) -> Result<usize> {
+    let value = matches.value_of(name).unwrap();
+    match value.parse() {
+        Ok(n) => Ok(n),
+        Err(_) => Err(Error::ArgError(format!(
+            "--{} must be a number but was {}",
+            name, value
+        ))),
+    }
+}
+
+/// Parses a keystream correction given as `LINE:OFFSET:TEXT`.
+fn parse_correction(correction: &str) -> Result<(usize, usize, &str)> {
+    let invalid = || {
+        Error::ArgError(format!(
+            "--correct must be LINE:OFFSET:TEXT but was {}",
+            correction
+        ))
+    };
+    let mut parts = correction.splitn(3, ':');
+    let line = parts
+        .next()
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(invalid)?;
+    let offset = parts
+        .next()
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(invalid)?;
+    let text = parts.next().ok_or_else(invalid)?;
+    Ok((line, offset, text))
+}
+
+/// Prints a ranked candidate with the key in hex and
+/// the start of the plaintext.
+fn print_candidate(rank: usize, key: &[u8], score: f64, plaintext: &[u8], line: Option<usize>) {
+    let key_hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+    match line {
+        Some(line) => print!("{:>2}. line {:<4} ", rank + 1, line),
+        None => print!("{:>2}. ", rank + 1),
+    }
+    println!(
+        "key {} score {:.2}: \"{}\"",
+        key_hex,
+        score,
+        preview(plaintext)
+    );
+}
+
+/// Returns the start of `plaintext` with special characters escaped.
+fn preview(plaintext: &[u8]) -> String {
+    const PREVIEW_LEN: usize = 60;
+
+    let mut preview: String = plaintext
+        .iter()
+        .take(PREVIEW_LEN)
+        .map(|b| match b {
+            b' '..=b'~' => char::from(*b).to_string(),
+            _ => std::ascii::escape_default(*b).map(char::from).collect(),
+        })
+        .collect();
+    if plaintext.len() > PREVIEW_LEN {
+        preview.push_str("...");
+    }
+    preview
+}
+
+fn get_padding(name: &str) -> Padding {
+    match name {
+        "pkcs7" => Padding::Pkcs7,
+        "zero" => Padding::Zero,
+        "iso7816" => Padding::Iso7816,
+        "x923" => Padding::AnsiX923,
+        "none" => Padding::None,
+        _ => unreachable!(),
+    }
+}
+
 fn get_input(file: Option<&str>) -> Result<Vec<u8>> {
     match file {
         Some(f) => util::read_bytes(f),
@@ -295,6 +756,20 @@ fn get_input(file: Option<&str>) -> Result<Vec<u8>> {
     }
 }
 
+fn open_input(file: Option<&str>) -> Result<Box<dyn Read>> {
+    match file {
+        Some(f) => Ok(Box::new(util::open(f)?)),
+        None => Ok(Box::new(io::stdin().lock())),
+    }
+}
+
+fn open_output(out: Option<&str>) -> Result<Box<dyn Write>> {
+    match out {
+        Some(outfile) => Ok(Box::new(util::create(outfile)?)),
+        None => Ok(Box::new(BufWriter::new(io::stdout().lock()))),
+    }
+}
+
 fn write_output(out: Option<&str>, data: &[u8]) -> Result<()> {
     match out {
         Some(outfile) => util::write_bytes(outfile, data),
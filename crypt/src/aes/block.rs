@@ -0,0 +1,289 @@
+use super::{check_key_len, BLOCK_SIZE};
+use crate::Result;
+use openssl::symm::{Crypter, Mode as CrypterMode};
+use std::cell::RefCell;
+
+/// A single AES block, laid out column by column as in FIPS-197.
+pub type Block = [u8; BLOCK_SIZE];
+
+/// A cipher that encrypts and decrypts fixed size blocks in place.
+/// Modes of operation are built on top of this.
+pub trait BlockCipher {
+    fn block_size(&self) -> usize;
+    fn encrypt_block(&self, block: &mut [u8]);
+    fn decrypt_block(&self, block: &mut [u8]);
+}
+
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+const INV_SBOX: [u8; 256] = invert(&SBOX);
+
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+const fn invert(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inv = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        inv[sbox[i] as usize] = i as u8;
+        i += 1;
+    }
+    inv
+}
+
+/// Pure Rust implementation of AES-128, AES-192 and AES-256.
+///
+/// The round keys and the individual round functions are exposed so that
+/// intermediate states and reduced-round variants can be analysed.
+pub struct Aes {
+    round_keys: Vec<Block>,
+}
+
+impl Aes {
+    pub fn new(key: &[u8]) -> Result<Self> {
+        check_key_len(key)?;
+        let nk = key.len() / 4;
+        let rounds = nk + 6;
+
+        // Key expansion, FIPS-197 section 5.2.
+        let mut words: Vec<[u8; 4]> = key.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]).collect();
+        for i in nk..4 * (rounds + 1) {
+            let mut temp = words[i - 1];
+            if i % nk == 0 {
+                temp.rotate_left(1);
+                temp = temp.map(|b| SBOX[b as usize]);
+                temp[0] ^= RCON[i / nk - 1];
+            } else if nk > 6 && i % nk == 4 {
+                temp = temp.map(|b| SBOX[b as usize]);
+            }
+            let prev = words[i - nk];
+            words.push([
+                prev[0] ^ temp[0],
+                prev[1] ^ temp[1],
+                prev[2] ^ temp[2],
+                prev[3] ^ temp[3],
+            ]);
+        }
+
+        let round_keys = words
+            .chunks(4)
+            .map(|w| {
+                let mut k = [0u8; BLOCK_SIZE];
+                for (i, word) in w.iter().enumerate() {
+                    k[i * 4..i * 4 + 4].copy_from_slice(word);
+                }
+                k
+            })
+            .collect();
+
+        Ok(Self { round_keys })
+    }
+
+    /// Number of rounds: 10, 12 or 14 depending on the key size.
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    /// The expanded key, one round key per round plus the initial one.
+    pub fn round_keys(&self) -> &[Block] {
+        &self.round_keys
+    }
+
+    /// Encrypts `state` using only the first `rounds` rounds, where the
+    /// last of them is a final round (i.e. without MixColumns).
+    pub fn encrypt_rounds(&self, state: &mut Block, rounds: usize) {
+        let rounds = rounds.min(self.rounds());
+        add_round_key(state, &self.round_keys[0]);
+        for round in 1..=rounds {
+            sub_bytes(state);
+            shift_rows(state);
+            if round != rounds {
+                mix_columns(state);
+            }
+            add_round_key(state, &self.round_keys[round]);
+        }
+    }
+
+    /// Inverse of `encrypt_rounds` given the same number of rounds.
+    pub fn decrypt_rounds(&self, state: &mut Block, rounds: usize) {
+        let rounds = rounds.min(self.rounds());
+        for round in (1..=rounds).rev() {
+            add_round_key(state, &self.round_keys[round]);
+            if round != rounds {
+                inv_mix_columns(state);
+            }
+            inv_shift_rows(state);
+            inv_sub_bytes(state);
+        }
+        add_round_key(state, &self.round_keys[0]);
+    }
+
+    /// Encrypts `block` and returns the state after the initial
+    /// AddRoundKey and after each of the rounds.
+    pub fn trace(&self, block: &Block) -> Vec<Block> {
+        let mut state = *block;
+        add_round_key(&mut state, &self.round_keys[0]);
+
+        let mut states = vec![state];
+        for round in 1..=self.rounds() {
+            sub_bytes(&mut state);
+            shift_rows(&mut state);
+            if round != self.rounds() {
+                mix_columns(&mut state);
+            }
+            add_round_key(&mut state, &self.round_keys[round]);
+            states.push(state);
+        }
+        states
+    }
+}
+
+impl BlockCipher for Aes {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let state: &mut Block = block.try_into().expect("block to have length 16");
+        self.encrypt_rounds(state, self.rounds());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let state: &mut Block = block.try_into().expect("block to have length 16");
+        self.decrypt_rounds(state, self.rounds());
+    }
+}
+
+pub fn add_round_key(state: &mut Block, key: &Block) {
+    for (s, k) in state.iter_mut().zip(key) {
+        *s ^= k;
+    }
+}
+
+pub fn sub_bytes(state: &mut Block) {
+    for b in state.iter_mut() {
+        *b = SBOX[*b as usize];
+    }
+}
+
+pub fn inv_sub_bytes(state: &mut Block) {
+    for b in state.iter_mut() {
+        *b = INV_SBOX[*b as usize];
+    }
+}
+
+/// Row `r` is rotated `r` steps to the left.
+pub fn shift_rows(state: &mut Block) {
+    let old = *state;
+    for c in 0..4 {
+        for r in 0..4 {
+            state[r + 4 * c] = old[r + 4 * ((c + r) % 4)];
+        }
+    }
+}
+
+pub fn inv_shift_rows(state: &mut Block) {
+    let old = *state;
+    for c in 0..4 {
+        for r in 0..4 {
+            state[r + 4 * ((c + r) % 4)] = old[r + 4 * c];
+        }
+    }
+}
+
+pub fn mix_columns(state: &mut Block) {
+    for col in state.chunks_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = gmul(a0, 2) ^ gmul(a1, 3) ^ a2 ^ a3;
+        col[1] = a0 ^ gmul(a1, 2) ^ gmul(a2, 3) ^ a3;
+        col[2] = a0 ^ a1 ^ gmul(a2, 2) ^ gmul(a3, 3);
+        col[3] = gmul(a0, 3) ^ a1 ^ a2 ^ gmul(a3, 2);
+    }
+}
+
+pub fn inv_mix_columns(state: &mut Block) {
+    for col in state.chunks_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = gmul(a0, 14) ^ gmul(a1, 11) ^ gmul(a2, 13) ^ gmul(a3, 9);
+        col[1] = gmul(a0, 9) ^ gmul(a1, 14) ^ gmul(a2, 11) ^ gmul(a3, 13);
+        col[2] = gmul(a0, 13) ^ gmul(a1, 9) ^ gmul(a2, 14) ^ gmul(a3, 11);
+        col[3] = gmul(a0, 11) ^ gmul(a1, 13) ^ gmul(a2, 9) ^ gmul(a3, 14);
+    }
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1.
+fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            p ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    p
+}
+
+/// AES backed by OpenSSL, used as a reference for the pure Rust implementation.
+pub struct OpensslAes {
+    encrypter: RefCell<Crypter>,
+    decrypter: RefCell<Crypter>,
+}
+
+impl OpensslAes {
+    pub fn new(key: &[u8]) -> Result<Self> {
+        let cipher = super::ecb_cipher(key)?;
+
+        let mut encrypter = Crypter::new(cipher, CrypterMode::Encrypt, key, None)?;
+        encrypter.pad(false);
+        let mut decrypter = Crypter::new(cipher, CrypterMode::Decrypt, key, None)?;
+        decrypter.pad(false);
+
+        Ok(Self {
+            encrypter: RefCell::new(encrypter),
+            decrypter: RefCell::new(decrypter),
+        })
+    }
+}
+
+impl BlockCipher for OpensslAes {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        crypt_block(&mut self.encrypter.borrow_mut(), block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        crypt_block(&mut self.decrypter.borrow_mut(), block);
+    }
+}
+
+fn crypt_block(crypter: &mut Crypter, block: &mut [u8]) {
+    let mut out = [0u8; BLOCK_SIZE * 2];
+    let n = crypter
+        .update(block, &mut out)
+        .expect("openssl to process a full block");
+    block.copy_from_slice(&out[..n]);
+}
@@ -0,0 +1,167 @@
+use super::{BlockCipher, OpensslAes, BLOCK_SIZE};
+use crate::{Error, Result};
+
+/// Layout of the counter block used in CTR mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Counter {
+    /// 64 bit nonce followed by a 64 bit little-endian block counter,
+    /// which is the format used by cryptopals.
+    LittleEndian64,
+    /// 128 bit big-endian counter where the nonce is the initial
+    /// counter block, which is the format used by NIST and OpenSSL.
+    BigEndian128,
+}
+
+impl Counter {
+    /// Length of the nonce expected by the format.
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            Counter::LittleEndian64 => 8,
+            Counter::BigEndian128 => BLOCK_SIZE,
+        }
+    }
+
+    /// Returns the counter block for the `n`th block of the keystream.
+    pub fn block(&self, nonce: &[u8], n: u64) -> [u8; BLOCK_SIZE] {
+        let mut block = [0u8; BLOCK_SIZE];
+        match self {
+            Counter::LittleEndian64 => {
+                block[..8].copy_from_slice(nonce);
+                block[8..].copy_from_slice(&n.to_le_bytes());
+            }
+            Counter::BigEndian128 => {
+                let mut initial = [0u8; BLOCK_SIZE];
+                initial.copy_from_slice(nonce);
+                let value = u128::from_be_bytes(initial).wrapping_add(n as u128);
+                block.copy_from_slice(&value.to_be_bytes());
+            }
+        }
+        block
+    }
+
+    pub(crate) fn check_nonce(&self, nonce: &[u8]) -> Result<()> {
+        if nonce.len() == self.nonce_len() {
+            Ok(())
+        } else {
+            Err(Error::ArgError(format!(
+                "invalid nonce length: must have length {} but was {}",
+                self.nonce_len(),
+                nonce.len()
+            )))
+        }
+    }
+}
+
+/// CTR keystream that can be read from any offset without
+/// generating the bytes that come before it.
+pub struct Keystream {
+    cipher: OpensslAes,
+    nonce: Vec<u8>,
+    counter: Counter,
+    position: u64,
+    // Index and value of the most recently generated keystream block.
+    current: Option<(u64, [u8; BLOCK_SIZE])>,
+}
+
+impl Keystream {
+    pub fn new(key: &[u8], nonce: &[u8], counter: Counter) -> Result<Self> {
+        counter.check_nonce(nonce)?;
+
+        Ok(Self {
+            cipher: OpensslAes::new(key)?,
+            nonce: nonce.to_vec(),
+            counter,
+            position: 0,
+            current: None,
+        })
+    }
+
+    /// Moves to byte `offset` of the keystream.
+    pub fn seek(&mut self, offset: u64) {
+        self.position = offset;
+    }
+
+    /// Current byte offset in the keystream.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the next `n` bytes of the keystream.
+    pub fn take(&mut self, n: usize) -> Result<Vec<u8>> {
+        let mut v = vec![0u8; n];
+        self.apply(&mut v)?;
+        Ok(v)
+    }
+
+    /// XOR:s the keystream, starting at the current position, into `data`
+    /// and advances the position by the length of `data`. Returns an error,
+    /// leaving `data` unchanged, if that would move past the end of the
+    /// keystream at `u64::MAX`.
+    pub fn apply(&mut self, data: &mut [u8]) -> Result<()> {
+        if self.position.checked_add(data.len() as u64).is_none() {
+            return Err(Error::ArgError(format!(
+                "keystream out of range: cannot read {} bytes at {}",
+                data.len(),
+                self.position
+            )));
+        }
+
+        for b in data.iter_mut() {
+            let index = self.position / BLOCK_SIZE as u64;
+            let offset = (self.position % BLOCK_SIZE as u64) as usize;
+            *b ^= self.block(index)[offset];
+            self.position += 1;
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, index: u64) -> &[u8; BLOCK_SIZE] {
+        match self.current {
+            Some((i, _)) if i == index => {}
+            _ => {
+                let mut block = self.counter.block(&self.nonce, index);
+                self.cipher.encrypt_block(&mut block);
+                self.current = Some((index, block));
+            }
+        }
+        &self.current.as_ref().unwrap().1
+    }
+}
+
+/// Replaces the plaintext at `offset` of a CTR encrypted `ciphertext` with
+/// `newtext`, re-encrypting only the affected bytes in place.
+pub fn edit(
+    ciphertext: &mut [u8],
+    key: &[u8],
+    nonce: &[u8],
+    counter: Counter,
+    offset: usize,
+    newtext: &[u8],
+) -> Result<()> {
+    let end = offset.checked_add(newtext.len()).ok_or_else(|| {
+        Error::ArgError(format!("edit out of range: offset {} is too large", offset))
+    })?;
+    if end > ciphertext.len() {
+        return Err(Error::ArgError(format!(
+            "edit out of range: {} is past the end of the ciphertext ({})",
+            end,
+            ciphertext.len()
+        )));
+    }
+
+    let mut keystream = Keystream::new(key, nonce, counter)?;
+    keystream.seek(offset as u64);
+
+    let target = &mut ciphertext[offset..end];
+    target.copy_from_slice(newtext);
+    keystream.apply(target)
+}
+
+/// Encrypts or decrypts `data` in CTR mode. Since the keystream is
+/// XOR:ed with the data both operations are the same.
+pub(super) fn ctr(nonce: &[u8], counter: Counter, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    let mut keystream = Keystream::new(key, nonce, counter)?;
+    let mut result = data.to_vec();
+    keystream.apply(&mut result)?;
+    Ok(result)
+}
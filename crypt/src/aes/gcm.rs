@@ -0,0 +1,109 @@
+use super::block::{Aes, BlockCipher};
+use super::BLOCK_SIZE;
+use crate::ghash::{ghash, Gf128};
+use crate::{Error, Result};
+
+/// Length of the authentication tag in bytes.
+pub const TAG_SIZE: usize = 16;
+
+/// Encrypts `plaintext` with AES-GCM and authenticates it together
+/// with `aad`. Returns the ciphertext and the authentication tag.
+pub fn encrypt(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<(Vec<u8>, [u8; TAG_SIZE])> {
+    let cipher = Aes::new(key)?;
+    let h = hash_key(&cipher);
+    let j0 = pre_counter_block(h, nonce)?;
+
+    let ciphertext = gctr(&cipher, inc32(j0), plaintext);
+    let tag = compute_tag(&cipher, h, j0, aad, &ciphertext);
+    Ok((ciphertext, tag))
+}
+
+/// Verifies the `tag` of `ciphertext` and `aad` and, if valid,
+/// returns the decrypted plaintext.
+pub fn decrypt(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    tag: &[u8],
+) -> Result<Vec<u8>> {
+    let cipher = Aes::new(key)?;
+    let h = hash_key(&cipher);
+    let j0 = pre_counter_block(h, nonce)?;
+
+    let expected = compute_tag(&cipher, h, j0, aad, ciphertext);
+    // Compare without short-circuiting on the first differing byte.
+    let diff = expected
+        .iter()
+        .zip(tag)
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+    if tag.len() != TAG_SIZE || diff != 0 {
+        return Err(Error::DataError("authentication tag mismatch".to_string()));
+    }
+
+    Ok(gctr(&cipher, inc32(j0), ciphertext))
+}
+
+/// The GHASH key H, i.e. the encryption of the zero block.
+pub fn hash_key<C: BlockCipher + ?Sized>(cipher: &C) -> Gf128 {
+    let mut block = [0u8; BLOCK_SIZE];
+    cipher.encrypt_block(&mut block);
+    Gf128::from_bytes(&block)
+}
+
+/// Derives the initial counter block J0 from the nonce. 96 bit nonces are
+/// used directly, any other length is hashed with GHASH.
+fn pre_counter_block(h: Gf128, nonce: &[u8]) -> Result<[u8; BLOCK_SIZE]> {
+    if nonce.is_empty() {
+        return Err(Error::ArgError("GCM nonce cannot be empty".to_string()));
+    }
+
+    if nonce.len() == 12 {
+        let mut j0 = [0u8; BLOCK_SIZE];
+        j0[..12].copy_from_slice(nonce);
+        j0[15] = 1;
+        Ok(j0)
+    } else {
+        Ok(ghash(h, &[], nonce).to_bytes())
+    }
+}
+
+fn compute_tag<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    h: Gf128,
+    j0: [u8; BLOCK_SIZE],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> [u8; TAG_SIZE] {
+    let s = ghash(h, aad, ciphertext).to_bytes();
+    let mut tag = [0u8; TAG_SIZE];
+    tag.copy_from_slice(&gctr(cipher, j0, &s));
+    tag
+}
+
+/// CTR mode where only the last 32 bits of the counter block are incremented.
+fn gctr<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    mut counter: [u8; BLOCK_SIZE],
+    data: &[u8],
+) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    for chunk in data.chunks(BLOCK_SIZE) {
+        let mut keystream = counter;
+        cipher.encrypt_block(&mut keystream);
+        result.extend(chunk.iter().zip(keystream.iter()).map(|(a, b)| a ^ b));
+        counter = inc32(counter);
+    }
+    result
+}
+
+fn inc32(mut block: [u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let n = u32::from_be_bytes([block[12], block[13], block[14], block[15]]).wrapping_add(1);
+    block[12..].copy_from_slice(&n.to_be_bytes());
+    block
+}
@@ -1,20 +1,41 @@
 use super::{Error, Result};
-use crate::gen::{random_data, random_key};
+use crate::gen::{DefaultSource, KeySource};
+use crate::pad::Padding;
 use crate::util;
-use openssl::symm::{decrypt, encrypt, Cipher};
-use rand::random;
+use openssl::symm::{Cipher, Crypter, Mode as CrypterMode};
 
+pub mod block;
+pub mod ctr;
+pub mod gcm;
+pub mod modes;
+pub mod oracle;
+pub mod stream;
 #[cfg(test)]
 mod tests;
 
+pub use block::{Aes, BlockCipher, OpensslAes};
+pub use ctr::Counter;
+pub use oracle::{Oracle, OracleBuilder};
+pub use stream::{decrypt_stream, encrypt_stream};
+
 /// Wrapper of the openssl crate:
 /// https://docs.rs/openssl/latest/openssl/index.html
 
 const BLOCK_SIZE: usize = 16;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Mode {
     ECB,
     CBC(Vec<u8>),
+    /// Counter mode using the given nonce and counter block layout.
+    CTR(Vec<u8>, Counter),
+    /// CFB with 8 bit feedback.
+    CFB8(Vec<u8>),
+    /// CFB with full block (128 bit) feedback.
+    CFB128(Vec<u8>),
+    OFB(Vec<u8>),
+    /// Propagating CBC.
+    PCBC(Vec<u8>),
 }
 
 fn check_block_len(b: &[u8]) -> Result<()> {
@@ -29,6 +50,21 @@ fn check_block_len(b: &[u8]) -> Result<()> {
     }
 }
 
+/// AES accepts 128, 192 and 256 bit keys.
+fn check_key_len(key: &[u8]) -> Result<()> {
+    match key.len() {
+        16 | 24 | 32 => Ok(()),
+        _ => Err(key_len_err(key)),
+    }
+}
+
+fn key_len_err(key: &[u8]) -> Error {
+    Error::ArgError(format!(
+        "invalid key length: must be 16, 24 or 32 but was {}",
+        key.len()
+    ))
+}
+
 fn check_data_len(data: &[u8]) -> Result<()> {
     match data.len() % BLOCK_SIZE {
         0 => Ok(()),
@@ -39,21 +75,24 @@ fn check_data_len(data: &[u8]) -> Result<()> {
     }
 }
 
+/// Encrypts `data` with a random key, between 5 and 10 random bytes
+/// before and after it, using either ECB or CBC chosen at random.
+/// Returns the encrypted data and whether ECB was used.
 pub fn encrypt_oracle(data: &[u8]) -> Result<(Vec<u8>, bool)> {
-    let key = random_key();
-    let mut v: Vec<u8> = Vec::new();
-    v.extend(random_data(5..11));
-    v.extend(data);
-    v.extend(random_data(5..11));
-
-    if random::<bool>() {
-        let b = encrypt_128(Mode::ECB, data, &key)?;
-        Ok((b, true))
-    } else {
-        let iv = random_key();
-        let b = encrypt_128(Mode::CBC(iv), data, &key)?;
-        Ok((b, false))
-    }
+    encrypt_oracle_with(data, &mut DefaultSource)
+}
+
+/// Same as `encrypt_oracle`, drawing the key, mode and bytes from `source`.
+pub fn encrypt_oracle_with<S: KeySource + ?Sized>(
+    data: &[u8],
+    source: &mut S,
+) -> Result<(Vec<u8>, bool)> {
+    let oracle = Oracle::builder()
+        .prefix(5..11)
+        .suffix(5..11)
+        .build_with(source)?;
+    let encrypted = oracle.encrypt(data)?;
+    Ok((encrypted, *oracle.mode() == Mode::ECB))
 }
 
 /// Try to detect AES mode by looking at the plaintext and encrypted data.
@@ -92,61 +131,161 @@ pub fn detection_oracle(plaintext: &[u8], encrypted: &[u8]) -> Result<String> {
     Ok("CBC".to_string())
 }
 
-pub fn encrypt_128(mode: Mode, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+/// Encrypts `data` with AES-128, AES-192 or AES-256 depending
+/// on the length of `key`, using PKCS#7 padding for the block modes.
+pub fn encrypt(mode: Mode, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    encrypt_padded(mode, Padding::Pkcs7, data, key)
+}
+
+/// Decrypts `data` with AES-128, AES-192 or AES-256 depending
+/// on the length of `key`, expecting PKCS#7 padding for the block modes.
+pub fn decrypt(mode: Mode, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    decrypt_padded(mode, Padding::Pkcs7, data, key)
+}
+
+/// Same as `encrypt` but with the given padding. Padding is only used by
+/// ECB, CBC and PCBC; the other modes are stream modes and never pad.
+pub fn encrypt_padded(mode: Mode, padding: Padding, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    check_key_len(key)?;
+
+    let pad = |data: &[u8]| -> Result<Vec<u8>> {
+        let padded = padding.pad(data, BLOCK_SIZE)?;
+        check_data_len(&padded)?;
+        Ok(padded)
+    };
+
     match mode {
-        Mode::ECB => encrypt_128_ecb(data, key),
-        Mode::CBC(iv) => encrypt_128_cbc(&iv, data, key),
+        Mode::ECB => encrypt_ecb(&pad(data)?, key),
+        Mode::CBC(iv) => encrypt_cbc(&iv, &pad(data)?, key),
+        Mode::PCBC(iv) => with_block_cipher(iv, pad(data)?, key, modes::pcbc_encrypt),
+        Mode::CTR(nonce, counter) => ctr::ctr(&nonce, counter, data, key),
+        Mode::CFB8(iv) => with_block_cipher(iv, data.to_vec(), key, modes::cfb8_encrypt),
+        Mode::CFB128(iv) => with_block_cipher(iv, data.to_vec(), key, modes::cfb_encrypt),
+        Mode::OFB(iv) => with_block_cipher(iv, data.to_vec(), key, modes::ofb),
     }
 }
 
-pub fn decrypt_128(mode: Mode, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
-    check_block_len(key)?;
+/// Same as `decrypt` but with the given padding.
+pub fn decrypt_padded(mode: Mode, padding: Padding, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    check_key_len(key)?;
+
+    let unpad = |decrypted: Vec<u8>| -> Result<Vec<u8>> {
+        Ok(padding.unpad(&decrypted, BLOCK_SIZE)?.to_vec())
+    };
 
     match mode {
-        Mode::ECB => decrypt_128_ecb(data, key),
-        Mode::CBC(iv) => decrypt_128_cbc(&iv, data, key),
+        Mode::ECB => unpad(decrypt_ecb(data, key)?),
+        Mode::CBC(iv) => unpad(decrypt_cbc(&iv, data, key)?),
+        Mode::PCBC(iv) => unpad(with_block_cipher(
+            iv,
+            data.to_vec(),
+            key,
+            modes::pcbc_decrypt,
+        )?),
+        Mode::CTR(nonce, counter) => ctr::ctr(&nonce, counter, data, key),
+        Mode::CFB8(iv) => with_block_cipher(iv, data.to_vec(), key, modes::cfb8_decrypt),
+        Mode::CFB128(iv) => with_block_cipher(iv, data.to_vec(), key, modes::cfb_decrypt),
+        Mode::OFB(iv) => with_block_cipher(iv, data.to_vec(), key, modes::ofb),
     }
 }
 
-fn encrypt_128_ecb(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+pub fn encrypt_128(mode: Mode, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     check_block_len(key)?;
+    encrypt(mode, data, key)
+}
 
-    let cipher = Cipher::aes_128_ecb();
-    match encrypt(cipher, key, None, data) {
+pub fn decrypt_128(mode: Mode, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    check_block_len(key)?;
+    decrypt(mode, data, key)
+}
+
+/// Runs one of the generic `modes` using the pure Rust AES implementation.
+fn with_block_cipher(
+    mut iv: Vec<u8>,
+    mut data: Vec<u8>,
+    key: &[u8],
+    f: fn(&Aes, &mut [u8], &mut [u8]) -> Result<()>,
+) -> Result<Vec<u8>> {
+    check_block_len(&iv)?;
+
+    let cipher = Aes::new(key)?;
+    f(&cipher, &mut iv, &mut data)?;
+    Ok(data)
+}
+
+fn ecb_cipher(key: &[u8]) -> Result<Cipher> {
+    match key.len() {
+        16 => Ok(Cipher::aes_128_ecb()),
+        24 => Ok(Cipher::aes_192_ecb()),
+        32 => Ok(Cipher::aes_256_ecb()),
+        _ => Err(key_len_err(key)),
+    }
+}
+
+fn cbc_cipher(key: &[u8]) -> Result<Cipher> {
+    match key.len() {
+        16 => Ok(Cipher::aes_128_cbc()),
+        24 => Ok(Cipher::aes_192_cbc()),
+        32 => Ok(Cipher::aes_256_cbc()),
+        _ => Err(key_len_err(key)),
+    }
+}
+
+/// OpenSSL crypter with its padding disabled, since padding is done by us.
+fn crypter(
+    cipher: Cipher,
+    direction: CrypterMode,
+    key: &[u8],
+    iv: Option<&[u8]>,
+) -> Result<Crypter> {
+    let mut crypter = Crypter::new(cipher, direction, key, iv)?;
+    crypter.pad(false);
+    Ok(crypter)
+}
+
+fn openssl_crypt(mut crypter: Crypter, data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = vec![0u8; data.len() + BLOCK_SIZE];
+    let mut n = crypter.update(data, &mut out)?;
+    n += crypter.finalize(&mut out[n..])?;
+    out.truncate(n);
+    Ok(out)
+}
+
+/// Encrypts block aligned `data` in ECB mode, without padding.
+fn encrypt_ecb(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    let crypter = crypter(ecb_cipher(key)?, CrypterMode::Encrypt, key, None)?;
+    match openssl_crypt(crypter, data) {
         Ok(v) => Ok(v),
         Err(err) => Err(Error::DataError(format!("error encrypting: {}", err))),
     }
 }
 
-fn encrypt_128_cbc(iv: &[u8], data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
-    check_block_len(key)?;
+fn encrypt_cbc(iv: &[u8], data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     check_block_len(iv)?;
 
-    let cipher = Cipher::aes_128_cbc();
-    match encrypt(cipher, key, Some(iv), data) {
+    let crypter = crypter(cbc_cipher(key)?, CrypterMode::Encrypt, key, Some(iv))?;
+    match openssl_crypt(crypter, data) {
         Ok(v) => Ok(v),
         Err(err) => Err(Error::DataError(format!("error encrypting: {}", err))),
     }
 }
 
-fn decrypt_128_ecb(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+fn decrypt_ecb(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     check_data_len(data)?;
-    check_block_len(key)?;
 
-    let cipher = Cipher::aes_128_ecb();
-    match decrypt(cipher, key, None, data) {
+    let crypter = crypter(ecb_cipher(key)?, CrypterMode::Decrypt, key, None)?;
+    match openssl_crypt(crypter, data) {
         Ok(v) => Ok(v),
-        Err(err) => return Err(Error::DataError(format!("error decrypting: {}", err))),
+        Err(err) => Err(Error::DataError(format!("error decrypting: {}", err))),
     }
 }
 
-fn decrypt_128_cbc(iv: &[u8], data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+fn decrypt_cbc(iv: &[u8], data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     check_data_len(data)?;
     check_block_len(iv)?;
-    check_block_len(key)?;
 
-    let cipher = Cipher::aes_128_cbc();
-    match decrypt(cipher, key, Some(iv), data) {
+    let crypter = crypter(cbc_cipher(key)?, CrypterMode::Decrypt, key, Some(iv))?;
+    match openssl_crypt(crypter, data) {
         Ok(v) => Ok(v),
         Err(err) => Err(Error::DataError(format!("error decrypting: {}", err))),
     }
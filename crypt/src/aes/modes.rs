@@ -0,0 +1,159 @@
+//! Modes of operation implemented generically over a block cipher.
+//!
+//! The functions work in place on `data` and update `iv` to the chaining
+//! value for the next call, so that long inputs can be processed in parts.
+//! Continuing only works when the previous parts were whole blocks.
+//! The IV must be one block long. PCBC returns an error unless `data` is
+//! whole blocks; the other modes accept any length.
+
+use super::block::BlockCipher;
+use crate::{Error, Result};
+
+/// CFB with 8 bit feedback, i.e. one cipher call per byte.
+pub fn cfb8_encrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &mut [u8],
+    data: &mut [u8],
+) -> Result<()> {
+    check_iv(cipher, iv)?;
+    let mut block = vec![0u8; cipher.block_size()];
+    for b in data.iter_mut() {
+        block.copy_from_slice(iv);
+        cipher.encrypt_block(&mut block);
+        *b ^= block[0];
+        shift_in(iv, *b);
+    }
+    Ok(())
+}
+
+pub fn cfb8_decrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &mut [u8],
+    data: &mut [u8],
+) -> Result<()> {
+    check_iv(cipher, iv)?;
+    let mut block = vec![0u8; cipher.block_size()];
+    for b in data.iter_mut() {
+        block.copy_from_slice(iv);
+        cipher.encrypt_block(&mut block);
+        shift_in(iv, *b);
+        *b ^= block[0];
+    }
+    Ok(())
+}
+
+/// CFB with full block feedback (CFB-128 for AES).
+pub fn cfb_encrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &mut [u8],
+    data: &mut [u8],
+) -> Result<()> {
+    check_iv(cipher, iv)?;
+    for chunk in data.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(iv);
+        xor_into(chunk, iv);
+        iv[..chunk.len()].copy_from_slice(chunk);
+    }
+    Ok(())
+}
+
+pub fn cfb_decrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &mut [u8],
+    data: &mut [u8],
+) -> Result<()> {
+    check_iv(cipher, iv)?;
+    for chunk in data.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(iv);
+        for (c, k) in chunk.iter_mut().zip(iv.iter_mut()) {
+            let plain = *c ^ *k;
+            *k = *c;
+            *c = plain;
+        }
+    }
+    Ok(())
+}
+
+/// OFB, which is the same operation for both encryption and decryption.
+pub fn ofb<C: BlockCipher + ?Sized>(cipher: &C, iv: &mut [u8], data: &mut [u8]) -> Result<()> {
+    check_iv(cipher, iv)?;
+    for chunk in data.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(iv);
+        xor_into(chunk, iv);
+    }
+    Ok(())
+}
+
+/// PCBC, where each block is chained with both the previous plaintext and
+/// ciphertext. Returns an error unless `data` is a multiple of the block size.
+pub fn pcbc_encrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &mut [u8],
+    data: &mut [u8],
+) -> Result<()> {
+    check_iv(cipher, iv)?;
+    check_whole_blocks(cipher, data)?;
+    let mut plain = vec![0u8; cipher.block_size()];
+    for block in data.chunks_mut(cipher.block_size()) {
+        plain.copy_from_slice(block);
+        xor_into(block, iv);
+        cipher.encrypt_block(block);
+        iv.copy_from_slice(block);
+        xor_into(iv, &plain);
+    }
+    Ok(())
+}
+
+pub fn pcbc_decrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &mut [u8],
+    data: &mut [u8],
+) -> Result<()> {
+    check_iv(cipher, iv)?;
+    check_whole_blocks(cipher, data)?;
+    let mut encrypted = vec![0u8; cipher.block_size()];
+    for block in data.chunks_mut(cipher.block_size()) {
+        encrypted.copy_from_slice(block);
+        cipher.decrypt_block(block);
+        xor_into(block, iv);
+        iv.copy_from_slice(block);
+        xor_into(iv, &encrypted);
+    }
+    Ok(())
+}
+
+fn check_iv<C: BlockCipher + ?Sized>(cipher: &C, iv: &[u8]) -> Result<()> {
+    if iv.len() == cipher.block_size() {
+        Ok(())
+    } else {
+        Err(Error::ArgError(format!(
+            "invalid IV length: must be {} but was {}",
+            cipher.block_size(),
+            iv.len()
+        )))
+    }
+}
+
+fn check_whole_blocks<C: BlockCipher + ?Sized>(cipher: &C, data: &[u8]) -> Result<()> {
+    if data.len().is_multiple_of(cipher.block_size()) {
+        Ok(())
+    } else {
+        Err(Error::DataError(format!(
+            "data not multiple of {}",
+            cipher.block_size()
+        )))
+    }
+}
+
+/// Shifts the register one byte to the left and appends `b`.
+fn shift_in(register: &mut [u8], b: u8) {
+    register.rotate_left(1);
+    let last = register.len() - 1;
+    register[last] = b;
+}
+
+fn xor_into(a: &mut [u8], b: &[u8]) {
+    for (x, y) in a.iter_mut().zip(b) {
+        *x ^= y;
+    }
+}
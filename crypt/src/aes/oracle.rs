@@ -0,0 +1,281 @@
+//! Configurable chosen-plaintext encryption oracles.
+//!
+//! An oracle encrypts `prefix || input || secret || suffix` under its key,
+//! where the prefix and suffix are random bytes and the secret is what an
+//! attack usually tries to recover. Everything is chosen when the oracle is
+//! built and kept for every call, and can be read back to check detection
+//! and attack code against the ground truth.
+
+use super::{decrypt_padded, encrypt_padded, Mode};
+use crate::gen::{DefaultSource, KeySource};
+use crate::pad::Padding;
+use crate::{Error, Result};
+use rand::Rng;
+use std::ops::Range;
+
+pub struct Oracle {
+    key: Vec<u8>,
+    mode: Mode,
+    padding: Padding,
+    prefix: Vec<u8>,
+    secret: Vec<u8>,
+    suffix: Vec<u8>,
+}
+
+impl Oracle {
+    /// Returns a builder for an oracle with a random 128 bit key, using
+    /// either ECB or CBC with a random IV, chosen at random, and PKCS#7
+    /// padding, without any prefix or suffix.
+    pub fn builder() -> OracleBuilder {
+        OracleBuilder::new()
+    }
+
+    /// Encrypts `input` together with the prefix, secret and suffix.
+    pub fn encrypt(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut data = self.prefix.clone();
+        data.extend_from_slice(input);
+        data.extend_from_slice(&self.secret);
+        data.extend_from_slice(&self.suffix);
+        encrypt_padded(self.mode.clone(), self.padding, &data, &self.key)
+    }
+
+    /// Decrypts `data` with the key, mode and padding of the oracle.
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        decrypt_padded(self.mode.clone(), self.padding, data, &self.key)
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    pub fn mode(&self) -> &Mode {
+        &self.mode
+    }
+
+    pub fn padding(&self) -> Padding {
+        self.padding
+    }
+
+    /// The random bytes before the input.
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    /// The secret right after the input.
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    /// The random bytes after the secret.
+    pub fn suffix(&self) -> &[u8] {
+        &self.suffix
+    }
+}
+
+pub struct OracleBuilder {
+    key: Option<Vec<u8>>,
+    mode: Option<Mode>,
+    padding: Padding,
+    prefix: Range<usize>,
+    secret: Vec<u8>,
+    suffix: Range<usize>,
+}
+
+impl OracleBuilder {
+    fn new() -> Self {
+        Self {
+            key: None,
+            mode: None,
+            padding: Padding::Pkcs7,
+            prefix: 0..0,
+            secret: Vec::new(),
+            suffix: 0..0,
+        }
+    }
+
+    /// Uses `key` instead of a random key.
+    pub fn key(mut self, key: &[u8]) -> Self {
+        self.key = Some(key.to_vec());
+        self
+    }
+
+    /// Uses `mode` instead of choosing between ECB and CBC at random.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Adds a random prefix with a length in `len`.
+    pub fn prefix(mut self, len: Range<usize>) -> Self {
+        self.prefix = len;
+        self
+    }
+
+    /// Adds `secret` after the input.
+    pub fn secret(mut self, secret: &[u8]) -> Self {
+        self.secret = secret.to_vec();
+        self
+    }
+
+    /// Adds a random suffix with a length in `len`, after the secret.
+    pub fn suffix(mut self, len: Range<usize>) -> Self {
+        self.suffix = len;
+        self
+    }
+
+    /// Builds the oracle, drawing what is random from the default source.
+    pub fn build(self) -> Result<Oracle> {
+        self.build_with(&mut DefaultSource)
+    }
+
+    /// Builds the oracle, drawing what is random from `source`.
+    pub fn build_with<S: KeySource + ?Sized>(self, source: &mut S) -> Result<Oracle> {
+        let key = match self.key {
+            Some(key) => key,
+            None => source.random_key(),
+        };
+        let mode = match self.mode {
+            Some(mode) => mode,
+            None if source.gen::<bool>() => Mode::ECB,
+            None => Mode::CBC(source.random_key()),
+        };
+
+        // Fail early on an invalid key or IV rather than on the first call.
+        if let Err(err) = encrypt_padded(mode.clone(), self.padding, &[], &key) {
+            return Err(Error::ArgError(format!("invalid oracle: {}", err)));
+        }
+
+        Ok(Oracle {
+            key,
+            mode,
+            padding: self.padding,
+            prefix: random_bytes(source, self.prefix),
+            secret: self.secret,
+            suffix: random_bytes(source, self.suffix),
+        })
+    }
+}
+
+fn random_bytes<S: KeySource + ?Sized>(source: &mut S, len: Range<usize>) -> Vec<u8> {
+    if len.is_empty() {
+        Vec::new()
+    } else {
+        source.random_data(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{decrypt, Counter};
+    use crate::attack::{OracleMode, OracleProfile};
+    use crate::gen::SeededSource;
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+
+    #[test]
+    fn test_oracle_ground_truth() {
+        let oracle = Oracle::builder()
+            .key(KEY)
+            .mode(Mode::ECB)
+            .prefix(5..11)
+            .secret(b"secret")
+            .suffix(1..3)
+            .build()
+            .unwrap();
+
+        assert_eq!(oracle.key(), KEY);
+        assert!((5..11).contains(&oracle.prefix().len()));
+        assert!((1..3).contains(&oracle.suffix().len()));
+
+        let encrypted = oracle.encrypt(b"input").unwrap();
+        let decrypted = decrypt(Mode::ECB, &encrypted, KEY).unwrap();
+
+        let mut expected = oracle.prefix().to_vec();
+        expected.extend_from_slice(b"inputsecret");
+        expected.extend_from_slice(oracle.suffix());
+        assert_eq!(decrypted, expected);
+        assert_eq!(oracle.decrypt(&encrypted).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_oracle_is_fixed() {
+        let oracle = Oracle::builder().prefix(0..32).build().unwrap();
+        assert_eq!(
+            oracle.encrypt(b"data").unwrap(),
+            oracle.encrypt(b"data").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_oracle_padding() {
+        let oracle = Oracle::builder()
+            .mode(Mode::CBC(vec![0; 16]))
+            .padding(Padding::AnsiX923)
+            .build()
+            .unwrap();
+        let encrypted = oracle.encrypt(b"data").unwrap();
+        assert!(decrypt(oracle.mode().clone(), &encrypted, oracle.key()).is_err());
+        assert_eq!(oracle.decrypt(&encrypted).unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_oracle_seeded() {
+        let build = |seed| {
+            Oracle::builder()
+                .prefix(0..32)
+                .suffix(0..32)
+                .build_with(&mut SeededSource::new(seed))
+                .unwrap()
+        };
+        let (a, b) = (build(1), build(1));
+        assert_eq!(a.key(), b.key());
+        assert_eq!(a.mode(), b.mode());
+        assert_eq!(a.prefix(), b.prefix());
+        assert_eq!(a.suffix(), b.suffix());
+        assert_ne!(a.key(), build(2).key());
+    }
+
+    #[test]
+    fn test_oracle_invalid() {
+        assert!(Oracle::builder().key(b"short").build().is_err());
+        assert!(Oracle::builder()
+            .mode(Mode::CBC(vec![0; 3]))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_oracle_profile() {
+        let modes = [
+            (Mode::ECB, OracleMode::Ecb, 16),
+            (Mode::CBC(vec![1; 16]), OracleMode::Cbc, 16),
+            (
+                Mode::CTR(vec![2; 8], Counter::LittleEndian64),
+                OracleMode::Ctr,
+                1,
+            ),
+        ];
+        for (mode, expected, block_size) in modes {
+            let oracle = Oracle::builder()
+                .mode(mode)
+                .prefix(0..40)
+                .secret(b"attack at dawn")
+                .suffix(0..20)
+                .build()
+                .unwrap();
+
+            let profile = OracleProfile::detect(|input| oracle.encrypt(input)).unwrap();
+            assert_eq!(profile.mode, expected);
+            assert_eq!(profile.block_size, block_size);
+            assert_eq!(profile.prefix_len, Some(oracle.prefix().len()));
+            let suffix_len = oracle.secret().len() + oracle.suffix().len();
+            assert_eq!(profile.suffix_len, Some(suffix_len));
+        }
+    }
+}
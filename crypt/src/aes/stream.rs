@@ -0,0 +1,207 @@
+use super::ctr::Keystream;
+use super::{
+    cbc_cipher, check_block_len, check_data_len, check_key_len, crypter, ecb_cipher, modes, Aes,
+    Mode, BLOCK_SIZE,
+};
+use crate::pad::Padding;
+use crate::{Error, Result};
+use openssl::symm::{Crypter, Mode as CrypterMode};
+use std::io::{ErrorKind, Read, Write};
+
+/// Number of bytes read from the input at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Encrypts everything read from `reader` and writes it to `writer`,
+/// one chunk at a time, so that memory use does not depend on the size
+/// of the input. Returns the number of bytes written.
+///
+/// As with `encrypt_padded`, `padding` is only used by the block modes.
+pub fn encrypt_stream<R: Read, W: Write>(
+    mode: Mode,
+    padding: Padding,
+    reader: R,
+    writer: W,
+    key: &[u8],
+) -> Result<u64> {
+    let (transform, end) = setup(mode, padding, key, CrypterMode::Encrypt)?;
+    run(transform, end, reader, writer)
+}
+
+/// Decrypts everything read from `reader` and writes it to `writer`.
+/// See `encrypt_stream`.
+pub fn decrypt_stream<R: Read, W: Write>(
+    mode: Mode,
+    padding: Padding,
+    reader: R,
+    writer: W,
+    key: &[u8],
+) -> Result<u64> {
+    let (transform, end) = setup(mode, padding, key, CrypterMode::Decrypt)?;
+    run(transform, end, reader, writer)
+}
+
+/// A mode of operation together with its chaining state.
+enum Transform {
+    /// ECB and CBC are done by OpenSSL, with padding handled by us.
+    Openssl(Crypter),
+    /// One of the generic `modes` using the pure Rust AES.
+    Generic {
+        cipher: Aes,
+        iv: Vec<u8>,
+        f: ModeFn,
+    },
+    Ctr(Keystream),
+}
+
+impl Transform {
+    /// Processes `data` in place. Unless it is the end of the input
+    /// `data` must be a multiple of the block size.
+    fn apply(&mut self, data: &mut [u8]) -> Result<()> {
+        match self {
+            Transform::Openssl(crypter) => {
+                let mut out = vec![0u8; data.len() + BLOCK_SIZE];
+                let n = crypter.update(data, &mut out)?;
+                data.copy_from_slice(&out[..n]);
+                Ok(())
+            }
+            Transform::Generic { cipher, iv, f } => f(cipher, iv, data),
+            Transform::Ctr(keystream) => keystream.apply(data),
+        }
+    }
+}
+
+/// What to do at the end of the stream.
+enum End {
+    /// Stream modes can process a partial final block.
+    Partial,
+    Pad(Padding),
+    Unpad(Padding),
+}
+
+type ModeFn = fn(&Aes, &mut [u8], &mut [u8]) -> Result<()>;
+
+fn setup(
+    mode: Mode,
+    padding: Padding,
+    key: &[u8],
+    direction: CrypterMode,
+) -> Result<(Transform, End)> {
+    check_key_len(key)?;
+
+    let encrypt = matches!(direction, CrypterMode::Encrypt);
+    let end = if encrypt {
+        End::Pad(padding)
+    } else {
+        End::Unpad(padding)
+    };
+    let generic = |iv: Vec<u8>, enc: ModeFn, dec: ModeFn| -> Result<Transform> {
+        check_block_len(&iv)?;
+        Ok(Transform::Generic {
+            cipher: Aes::new(key)?,
+            iv,
+            f: if encrypt { enc } else { dec },
+        })
+    };
+
+    let setup = match mode {
+        Mode::ECB => (openssl(ecb_cipher(key)?, direction, key, None)?, end),
+        Mode::CBC(iv) => {
+            check_block_len(&iv)?;
+            (openssl(cbc_cipher(key)?, direction, key, Some(&iv))?, end)
+        }
+        Mode::PCBC(iv) => {
+            let transform = generic(iv, modes::pcbc_encrypt, modes::pcbc_decrypt)?;
+            (transform, end)
+        }
+        Mode::CFB8(iv) => {
+            let transform = generic(iv, modes::cfb8_encrypt, modes::cfb8_decrypt)?;
+            (transform, End::Partial)
+        }
+        Mode::CFB128(iv) => {
+            let transform = generic(iv, modes::cfb_encrypt, modes::cfb_decrypt)?;
+            (transform, End::Partial)
+        }
+        Mode::OFB(iv) => (generic(iv, modes::ofb, modes::ofb)?, End::Partial),
+        Mode::CTR(nonce, counter) => {
+            let keystream = Keystream::new(key, &nonce, counter)?;
+            (Transform::Ctr(keystream), End::Partial)
+        }
+    };
+    Ok(setup)
+}
+
+fn openssl(
+    cipher: openssl::symm::Cipher,
+    direction: CrypterMode,
+    key: &[u8],
+    iv: Option<&[u8]>,
+) -> Result<Transform> {
+    Ok(Transform::Openssl(crypter(cipher, direction, key, iv)?))
+}
+
+fn run<R: Read, W: Write>(
+    mut transform: Transform,
+    end: End,
+    mut reader: R,
+    mut writer: W,
+) -> Result<u64> {
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let mut pending: Vec<u8> = Vec::with_capacity(CHUNK_SIZE * 2);
+    let mut written = 0;
+
+    loop {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::DataError(format!("error reading input: {}", err))),
+        };
+        pending.extend_from_slice(&chunk[..n]);
+
+        // Only whole blocks can be processed before the end of the input, and
+        // when removing padding the last block is held back since it might
+        // be the final one.
+        let mut ready = pending.len() - pending.len() % BLOCK_SIZE;
+        if matches!(end, End::Unpad(_)) && ready == pending.len() {
+            ready = ready.saturating_sub(BLOCK_SIZE);
+        }
+        if ready < CHUNK_SIZE {
+            continue;
+        }
+
+        transform.apply(&mut pending[..ready])?;
+        written += write(&mut writer, &pending[..ready])?;
+        pending.drain(..ready);
+    }
+
+    let last = match end {
+        End::Partial => {
+            transform.apply(&mut pending)?;
+            pending
+        }
+        End::Pad(padding) => {
+            let mut padded = padding.pad(&pending, BLOCK_SIZE)?;
+            check_data_len(&padded)?;
+            transform.apply(&mut padded)?;
+            padded
+        }
+        End::Unpad(padding) => {
+            check_data_len(&pending)?;
+            transform.apply(&mut pending)?;
+            padding.unpad(&pending, BLOCK_SIZE)?.to_vec()
+        }
+    };
+    written += write(&mut writer, &last)?;
+
+    match writer.flush() {
+        Ok(_) => Ok(written),
+        Err(err) => Err(Error::DataError(format!("error writing output: {}", err))),
+    }
+}
+
+fn write<W: Write>(writer: &mut W, b: &[u8]) -> Result<u64> {
+    match writer.write_all(b) {
+        Ok(_) => Ok(b.len() as u64),
+        Err(err) => Err(Error::DataError(format!("error writing output: {}", err))),
+    }
+}
@@ -1,4 +1,7 @@
 use super::*;
+use crate::encoding::{base64::Base64, hex::Hex, Decoder};
+use crate::gen::SeededSource;
+use crate::pad::Padding;
 
 const KEY: &[u8] = b"YELLOW SUBMARINE";
 
@@ -21,6 +24,23 @@ fn test_encrypt_oracle() {
     let data = b"Some Crypto Text and something else";
     let (encrypted, _) = encrypt_oracle(data).unwrap();
     assert!(encrypted.len() > 0);
+
+    // Between 10 and 20 random bytes are added around the data,
+    // so 6 bytes of data always fill two blocks when padded.
+    for _ in 0..10 {
+        let (encrypted, _) = encrypt_oracle(b"sixsix").unwrap();
+        assert_eq!(encrypted.len(), 32);
+    }
+
+    // The same source gives the same result
+    let mut a = SeededSource::new(11);
+    let mut b = SeededSource::new(11);
+    for _ in 0..10 {
+        assert_eq!(
+            encrypt_oracle_with(data, &mut a).unwrap(),
+            encrypt_oracle_with(data, &mut b).unwrap()
+        );
+    }
 }
 
 #[test]
@@ -40,3 +60,727 @@ fn test_decrypt_128_cbc() {
     let decrypted = decrypt_128(Mode::CBC(KEY.to_vec()), &encrypted, KEY).unwrap();
     assert_eq!(decrypted, data);
 }
+
+fn hex(s: &str) -> Vec<u8> {
+    Hex::new().decode(s).unwrap()
+}
+
+#[test]
+fn test_ctr_little_endian_64() {
+    // https://cryptopals.com/sets/3/challenges/18
+    let encrypted = Base64::new()
+        .decode("L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==")
+        .unwrap();
+    let nonce = vec![0u8; 8];
+
+    let decrypted =
+        decrypt_128(Mode::CTR(nonce, Counter::LittleEndian64), &encrypted, KEY).unwrap();
+    assert_eq!(
+        decrypted,
+        b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
+    );
+}
+
+#[test]
+fn test_ctr_big_endian_128() {
+    // NIST SP 800-38A, F.5.1 CTR-AES128.Encrypt
+    let key = hex("2b7e151628aed2a6abf7158809cf4f3c");
+    let nonce = hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    let plaintext = hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710");
+    let expected = hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee");
+
+    let encrypted = encrypt_128(Mode::CTR(nonce, Counter::BigEndian128), &plaintext, &key).unwrap();
+    assert_eq!(encrypted, expected);
+}
+
+#[test]
+fn test_ctr_matches_openssl() {
+    let nonce = hex("000102030405060708090a0b0c0dfffe");
+    let data = b"Some Crypto Text that does not end on a block boundary";
+
+    let encrypted =
+        encrypt_128(Mode::CTR(nonce.clone(), Counter::BigEndian128), data, KEY).unwrap();
+    let expected = openssl::symm::encrypt(
+        openssl::symm::Cipher::aes_128_ctr(),
+        KEY,
+        Some(&nonce),
+        data,
+    )
+    .unwrap();
+    assert_eq!(encrypted, expected);
+}
+
+#[test]
+fn test_ctr_invalid_nonce() {
+    let res = encrypt_128(
+        Mode::CTR(vec![0; 16], Counter::LittleEndian64),
+        b"data",
+        KEY,
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_ctr_keystream_seek() {
+    let nonce = [7u8; 8];
+    let mut keystream = ctr::Keystream::new(KEY, &nonce, Counter::LittleEndian64).unwrap();
+    let full = keystream.take(100).unwrap();
+    assert_eq!(keystream.position(), 100);
+
+    for offset in [0, 1, 15, 16, 17, 42, 99] {
+        keystream.seek(offset as u64);
+        let part = keystream.take(100 - offset).unwrap();
+        assert_eq!(part, full[offset..]);
+    }
+}
+
+#[test]
+fn test_ctr_keystream_end() {
+    let nonce = [7u8; 8];
+    let mut keystream = ctr::Keystream::new(KEY, &nonce, Counter::LittleEndian64).unwrap();
+    keystream.seek(u64::MAX - 1);
+    assert_eq!(keystream.take(1).unwrap().len(), 1);
+    assert_eq!(keystream.position(), u64::MAX);
+    assert!(keystream.take(0).unwrap().is_empty());
+
+    let mut data = [1u8; 2];
+    assert!(keystream.apply(&mut data).is_err());
+    assert_eq!(data, [1, 1]);
+    assert_eq!(keystream.position(), u64::MAX);
+
+    keystream.seek(u64::MAX - 1);
+    assert!(keystream.take(2).is_err());
+}
+
+#[test]
+fn test_ctr_edit() {
+    let nonce = [0u8; 8];
+    let data = b"Some Crypto Text that spans more than a single block";
+    let mut encrypted = encrypt_128(
+        Mode::CTR(nonce.to_vec(), Counter::LittleEndian64),
+        data,
+        KEY,
+    )
+    .unwrap();
+
+    ctr::edit(
+        &mut encrypted,
+        KEY,
+        &nonce,
+        Counter::LittleEndian64,
+        12,
+        b"Bits",
+    )
+    .unwrap();
+
+    let decrypted = decrypt_128(
+        Mode::CTR(nonce.to_vec(), Counter::LittleEndian64),
+        &encrypted,
+        KEY,
+    )
+    .unwrap();
+    assert_eq!(
+        decrypted,
+        b"Some Crypto Bits that spans more than a single block"
+    );
+}
+
+#[test]
+fn test_ctr_edit_out_of_range() {
+    let nonce = [0u8; 8];
+    let mut encrypted = vec![0u8; 10];
+    let res = ctr::edit(
+        &mut encrypted,
+        KEY,
+        &nonce,
+        Counter::LittleEndian64,
+        8,
+        b"abc",
+    );
+    assert!(res.is_err());
+
+    let res = ctr::edit(
+        &mut encrypted,
+        KEY,
+        &nonce,
+        Counter::LittleEndian64,
+        usize::MAX,
+        b"abc",
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_aes_fips_197() {
+    // FIPS-197, appendix C
+    let plaintext = hex("00112233445566778899aabbccddeeff");
+    let tests = [
+        (
+            "000102030405060708090a0b0c0d0e0f",
+            "69c4e0d86a7b0430d8cdb78070b4c55a",
+        ),
+        (
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "dda97ca4864cdfe06eaf70a0ec0d7191",
+        ),
+        (
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "8ea2b7ca516745bfeafc49904b496089",
+        ),
+    ];
+
+    for (key, expected) in tests {
+        let aes = Aes::new(&hex(key)).unwrap();
+        let mut block = plaintext.clone();
+        aes.encrypt_block(&mut block);
+        assert_eq!(block, hex(expected));
+
+        aes.decrypt_block(&mut block);
+        assert_eq!(block, plaintext);
+    }
+}
+
+#[test]
+fn test_aes_round_keys() {
+    // FIPS-197, appendix A.1
+    let aes = Aes::new(&hex("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+    assert_eq!(aes.rounds(), 10);
+    assert_eq!(aes.round_keys().len(), 11);
+    assert_eq!(
+        aes.round_keys()[10].to_vec(),
+        hex("d014f9a8c9ee2589e13f0cc8b6630ca6")
+    );
+}
+
+#[test]
+fn test_aes_trace() {
+    // FIPS-197, appendix C.1
+    let aes = Aes::new(&hex("000102030405060708090a0b0c0d0e0f")).unwrap();
+    let mut block = [0u8; 16];
+    block.copy_from_slice(&hex("00112233445566778899aabbccddeeff"));
+
+    let states = aes.trace(&block);
+    assert_eq!(states.len(), 11);
+    assert_eq!(states[0].to_vec(), hex("00102030405060708090a0b0c0d0e0f0"));
+    assert_eq!(states[1].to_vec(), hex("89d810e8855ace682d1843d8cb128fe4"));
+    assert_eq!(states[10].to_vec(), hex("69c4e0d86a7b0430d8cdb78070b4c55a"));
+}
+
+#[test]
+fn test_aes_reduced_rounds() {
+    let aes = Aes::new(KEY).unwrap();
+    let block = *b"Some Crypto Text";
+
+    for rounds in 1..=aes.rounds() {
+        let mut state = block;
+        aes.encrypt_rounds(&mut state, rounds);
+        assert_ne!(state, block);
+
+        aes.decrypt_rounds(&mut state, rounds);
+        assert_eq!(state, block);
+    }
+
+    let mut state = block;
+    aes.encrypt_rounds(&mut state, aes.rounds());
+    assert_eq!(state, aes.trace(&block)[aes.rounds()]);
+}
+
+#[test]
+fn test_aes_matches_openssl() {
+    for key_len in [16, 24, 32] {
+        let key = crate::gen::random_data(key_len..key_len + 1);
+        let aes = Aes::new(&key).unwrap();
+        let reference = OpensslAes::new(&key).unwrap();
+
+        for _ in 0..20 {
+            let block = crate::gen::random_data(16..17);
+
+            let mut a = block.clone();
+            let mut b = block.clone();
+            aes.encrypt_block(&mut a);
+            reference.encrypt_block(&mut b);
+            assert_eq!(a, b);
+
+            aes.decrypt_block(&mut a);
+            reference.decrypt_block(&mut b);
+            assert_eq!(a, block);
+            assert_eq!(b, block);
+        }
+    }
+}
+
+#[test]
+fn test_aes_invalid_key() {
+    assert!(Aes::new(b"too short").is_err());
+    assert!(OpensslAes::new(b"too short").is_err());
+}
+
+#[test]
+fn test_encrypt_ecb_key_sizes() {
+    // NIST SP 800-38A, F.1.1, F.1.3 and F.1.5
+    let plaintext = hex("6bc1bee22e409f96e93d7e117393172a");
+    let tests = [
+        (
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "3ad77bb40d7a3660a89ecaf32466ef97",
+        ),
+        (
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "bd334f1d6e45f25ff712a214571fa5cc",
+        ),
+        (
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "f3eed1bdb5d2a03c064b5a7e3db181f8",
+        ),
+    ];
+
+    for (key, expected) in tests {
+        let key = hex(key);
+        let encrypted = encrypt(Mode::ECB, &plaintext, &key).unwrap();
+        assert_eq!(encrypted[..16], hex(expected));
+
+        let decrypted = decrypt(Mode::ECB, &encrypted, &key).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+}
+
+#[test]
+fn test_encrypt_cbc_key_sizes() {
+    let data = b"Some Crypto Text and something else";
+    for key_len in [16, 24, 32] {
+        let key = vec![0x42; key_len];
+        let encrypted = encrypt(Mode::CBC(KEY.to_vec()), data, &key).unwrap();
+        let decrypted = decrypt(Mode::CBC(KEY.to_vec()), &encrypted, &key).unwrap();
+        assert_eq!(decrypted, data);
+    }
+}
+
+#[test]
+fn test_ctr_aes_256() {
+    // NIST SP 800-38A, F.5.5 CTR-AES256.Encrypt
+    let key = hex("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4");
+    let nonce = hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    let plaintext = hex("6bc1bee22e409f96e93d7e117393172a");
+
+    let encrypted = encrypt(Mode::CTR(nonce, Counter::BigEndian128), &plaintext, &key).unwrap();
+    assert_eq!(encrypted, hex("601ec313775789a5b7a7f504bbf3d228"));
+}
+
+#[test]
+fn test_encrypt_invalid_key_len() {
+    let data = b"Some Crypto Text";
+    assert!(encrypt(Mode::ECB, data, b"too short").is_err());
+    assert!(encrypt_128(Mode::ECB, data, &[0; 24]).is_err());
+    assert!(decrypt_128(Mode::ECB, &[0; 16], &[0; 32]).is_err());
+}
+
+// NIST SP 800-38A, appendix F
+const NIST_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+const NIST_IV: &str = "000102030405060708090a0b0c0d0e0f";
+const NIST_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+#[test]
+fn test_cfb8_nist() {
+    // F.3.7 CFB8-AES128.Encrypt
+    let plaintext = hex(&NIST_PLAINTEXT[..36]);
+    let expected = hex("3b79424c9c0dd436bace9e0ed4586a4f32b9");
+
+    let encrypted = encrypt(Mode::CFB8(hex(NIST_IV)), &plaintext, &hex(NIST_KEY)).unwrap();
+    assert_eq!(encrypted, expected);
+
+    let decrypted = decrypt(Mode::CFB8(hex(NIST_IV)), &encrypted, &hex(NIST_KEY)).unwrap();
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn test_cfb128_nist() {
+    // F.3.13 CFB128-AES128.Encrypt
+    let expected = hex("3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6");
+
+    let encrypted = encrypt(
+        Mode::CFB128(hex(NIST_IV)),
+        &hex(NIST_PLAINTEXT),
+        &hex(NIST_KEY),
+    )
+    .unwrap();
+    assert_eq!(encrypted, expected);
+
+    let decrypted = decrypt(Mode::CFB128(hex(NIST_IV)), &encrypted, &hex(NIST_KEY)).unwrap();
+    assert_eq!(decrypted, hex(NIST_PLAINTEXT));
+}
+
+#[test]
+fn test_ofb_nist() {
+    // F.4.1 OFB-AES128.Encrypt
+    let expected = hex("3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed8259740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e");
+
+    let encrypted = encrypt(
+        Mode::OFB(hex(NIST_IV)),
+        &hex(NIST_PLAINTEXT),
+        &hex(NIST_KEY),
+    )
+    .unwrap();
+    assert_eq!(encrypted, expected);
+
+    let decrypted = decrypt(Mode::OFB(hex(NIST_IV)), &encrypted, &hex(NIST_KEY)).unwrap();
+    assert_eq!(decrypted, hex(NIST_PLAINTEXT));
+}
+
+#[test]
+fn test_stream_modes_match_openssl() {
+    use openssl::symm::Cipher;
+
+    let data = b"Some Crypto Text that does not end on a block boundary";
+    for key_len in [16, 24, 32] {
+        let key = vec![0x42; key_len];
+        let tests = [
+            (
+                Mode::CFB8(KEY.to_vec()),
+                [
+                    Cipher::aes_128_cfb8(),
+                    Cipher::aes_192_cfb8(),
+                    Cipher::aes_256_cfb8(),
+                ],
+            ),
+            (
+                Mode::CFB128(KEY.to_vec()),
+                [
+                    Cipher::aes_128_cfb128(),
+                    Cipher::aes_192_cfb128(),
+                    Cipher::aes_256_cfb128(),
+                ],
+            ),
+            (
+                Mode::OFB(KEY.to_vec()),
+                [
+                    Cipher::aes_128_ofb(),
+                    Cipher::aes_192_ofb(),
+                    Cipher::aes_256_ofb(),
+                ],
+            ),
+        ];
+
+        for (mode, ciphers) in tests {
+            let cipher = ciphers[key_len / 8 - 2];
+            let expected = openssl::symm::encrypt(cipher, &key, Some(KEY), data).unwrap();
+            assert_eq!(encrypt(mode, data, &key).unwrap(), expected);
+        }
+    }
+}
+
+#[test]
+fn test_pcbc() {
+    let data = b"Some Crypto Text and something else";
+    let encrypted = encrypt(Mode::PCBC(KEY.to_vec()), data, KEY).unwrap();
+    assert_eq!(encrypted.len(), 48);
+
+    // The first block is the same as for CBC
+    let cbc = encrypt(Mode::CBC(KEY.to_vec()), data, KEY).unwrap();
+    assert_eq!(encrypted[..16], cbc[..16]);
+    assert_ne!(encrypted[16..], cbc[16..]);
+
+    let decrypted = decrypt(Mode::PCBC(KEY.to_vec()), &encrypted, KEY).unwrap();
+    assert_eq!(decrypted, data);
+}
+
+#[test]
+fn test_pcbc_error_propagates() {
+    let data = b"Some Crypto Text".repeat(4);
+    let mut encrypted = encrypt(Mode::PCBC(KEY.to_vec()), &data, KEY).unwrap();
+    encrypted[0] ^= 1;
+
+    // Unlike CBC, a single flipped bit garbles every following block.
+    let mut iv = KEY.to_vec();
+    let cipher = Aes::new(KEY).unwrap();
+    modes::pcbc_decrypt(&cipher, &mut iv, &mut encrypted).unwrap();
+    for block in encrypted.chunks(16) {
+        assert_ne!(block, b"Some Crypto Text");
+    }
+}
+
+#[test]
+fn test_pcbc_unaligned() {
+    let cipher = Aes::new(KEY).unwrap();
+    let mut data = b"Not a whole block".to_vec();
+    assert!(modes::pcbc_encrypt(&cipher, &mut KEY.to_vec(), &mut data).is_err());
+    assert!(modes::pcbc_decrypt(&cipher, &mut KEY.to_vec(), &mut data).is_err());
+    assert_eq!(data, b"Not a whole block");
+    assert!(decrypt(Mode::PCBC(KEY.to_vec()), &data, KEY).is_err());
+}
+
+#[test]
+fn test_modes_invalid_iv() {
+    let cipher = Aes::new(KEY).unwrap();
+    let modes: [ModeFn; 7] = [
+        modes::cfb8_encrypt::<dyn BlockCipher>,
+        modes::cfb8_decrypt::<dyn BlockCipher>,
+        modes::cfb_encrypt::<dyn BlockCipher>,
+        modes::cfb_decrypt::<dyn BlockCipher>,
+        modes::ofb::<dyn BlockCipher>,
+        modes::pcbc_encrypt::<dyn BlockCipher>,
+        modes::pcbc_decrypt::<dyn BlockCipher>,
+    ];
+    for f in modes {
+        let mut data = b"Some Crypto Text".to_vec();
+        assert!(f(&cipher, &mut [0; 8], &mut data).is_err());
+        assert!(f(&cipher, &mut [0; 17], &mut data).is_err());
+        assert!(f(&cipher, &mut [], &mut data).is_err());
+        assert_eq!(data, b"Some Crypto Text");
+    }
+}
+
+type ModeFn = fn(&(dyn BlockCipher + 'static), &mut [u8], &mut [u8]) -> Result<()>;
+
+#[test]
+fn test_modes_generic_over_block_cipher() {
+    let data = b"Some Crypto Text".repeat(3);
+    let aes = Aes::new(KEY).unwrap();
+    let reference = OpensslAes::new(KEY).unwrap();
+
+    let modes: [ModeFn; 4] = [
+        modes::cfb8_encrypt::<dyn BlockCipher>,
+        modes::cfb_encrypt::<dyn BlockCipher>,
+        modes::ofb::<dyn BlockCipher>,
+        modes::pcbc_encrypt::<dyn BlockCipher>,
+    ];
+    for f in modes {
+        let (mut a, mut b) = (data.clone(), data.clone());
+        f(&aes, &mut KEY.to_vec(), &mut a).unwrap();
+        f(&reference, &mut KEY.to_vec(), &mut b).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, data);
+    }
+}
+
+#[test]
+fn test_gcm_nist() {
+    // Test cases 2-5 from "The Galois/Counter Mode of Operation (GCM)"
+    let plaintext = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+    let tests = [
+        (
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "00000000000000000000000000000000",
+            "0388dace60b6a392f328c2b971b2fe78",
+            "ab6e47d42cec13bdf53a67b21257bddf",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbaddecaf888",
+            "",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985",
+            "4d5c2af327cd64a62cf35abd2ba6fab4",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbaddecaf888",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            plaintext,
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+            "5bc94fbc3221a5db94fae95ae7121a47",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbad",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            plaintext,
+            "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c742373806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+            "3612d2e79e3b0785561be14aaca2fccb",
+        ),
+    ];
+
+    for (key, nonce, aad, plaintext, ciphertext, tag) in tests {
+        let (key, nonce, aad) = (hex(key), hex(nonce), hex(aad));
+        let (encrypted, actual_tag) = gcm::encrypt(&key, &nonce, &aad, &hex(plaintext)).unwrap();
+        assert_eq!(encrypted, hex(ciphertext));
+        assert_eq!(actual_tag.to_vec(), hex(tag));
+
+        let decrypted = gcm::decrypt(&key, &nonce, &aad, &encrypted, &actual_tag).unwrap();
+        assert_eq!(decrypted, hex(plaintext));
+    }
+}
+
+#[test]
+fn test_gcm_matches_openssl() {
+    use openssl::symm::{encrypt_aead, Cipher};
+
+    let nonce = b"unique nonce";
+    let aad = b"associated data";
+    let data = b"Some Crypto Text that does not end on a block boundary";
+    let ciphers = [
+        Cipher::aes_128_gcm(),
+        Cipher::aes_192_gcm(),
+        Cipher::aes_256_gcm(),
+    ];
+
+    for (key_len, cipher) in [16, 24, 32].into_iter().zip(ciphers) {
+        let key = vec![0x42; key_len];
+        let (encrypted, tag) = gcm::encrypt(&key, nonce, aad, data).unwrap();
+
+        let mut expected_tag = [0u8; 16];
+        let expected =
+            encrypt_aead(cipher, &key, Some(nonce), aad, data, &mut expected_tag).unwrap();
+        assert_eq!(encrypted, expected);
+        assert_eq!(tag, expected_tag);
+    }
+}
+
+#[test]
+fn test_gcm_invalid_tag() {
+    let nonce = b"unique nonce";
+    let (mut encrypted, tag) = gcm::encrypt(KEY, nonce, b"aad", b"Some Crypto Text").unwrap();
+
+    assert!(gcm::decrypt(KEY, nonce, b"other aad", &encrypted, &tag).is_err());
+    assert!(gcm::decrypt(KEY, nonce, b"aad", &encrypted, &tag[..12]).is_err());
+
+    encrypted[0] ^= 1;
+    assert!(gcm::decrypt(KEY, nonce, b"aad", &encrypted, &tag).is_err());
+}
+
+#[test]
+fn test_gcm_hash_key() {
+    let cipher = Aes::new(&[0; 16]).unwrap();
+    assert_eq!(
+        gcm::hash_key(&cipher).to_bytes().to_vec(),
+        hex("66e94bd4ef8a2c3b884cfa59ca342b2e")
+    );
+}
+
+/// Reader that returns at most a few bytes per read.
+struct Trickle<'a>(&'a [u8]);
+
+impl std::io::Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.0.len().min(buf.len()).min(7);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+fn stream_modes() -> Vec<fn() -> Mode> {
+    vec![
+        || Mode::ECB,
+        || Mode::CBC(KEY.to_vec()),
+        || Mode::PCBC(KEY.to_vec()),
+        || Mode::CFB8(KEY.to_vec()),
+        || Mode::CFB128(KEY.to_vec()),
+        || Mode::OFB(KEY.to_vec()),
+        || Mode::CTR(vec![0; 8], Counter::LittleEndian64),
+    ]
+}
+
+#[test]
+fn test_stream_matches_buffered() {
+    let long = b"Some Crypto Text".repeat(4200);
+    for len in [0, 1, 15, 16, 17, 100, long.len() - 3] {
+        let data = &long[..len];
+        for mode in stream_modes() {
+            let expected = encrypt(mode(), data, KEY).unwrap();
+
+            let mut encrypted = Vec::new();
+            let n = encrypt_stream(mode(), Padding::Pkcs7, data, &mut encrypted, KEY).unwrap();
+            assert_eq!(encrypted, expected);
+            assert_eq!(n as usize, expected.len());
+
+            let mut decrypted = Vec::new();
+            decrypt_stream(mode(), Padding::Pkcs7, &encrypted[..], &mut decrypted, KEY).unwrap();
+            assert_eq!(decrypted, data);
+        }
+    }
+}
+
+#[test]
+fn test_stream_small_reads() {
+    let data = b"Some Crypto Text".repeat(4200);
+    for mode in stream_modes() {
+        let mut encrypted = Vec::new();
+        encrypt_stream(mode(), Padding::Pkcs7, Trickle(&data), &mut encrypted, KEY).unwrap();
+        assert_eq!(encrypted, encrypt(mode(), &data, KEY).unwrap());
+
+        let mut decrypted = Vec::new();
+        decrypt_stream(
+            mode(),
+            Padding::Pkcs7,
+            Trickle(&encrypted),
+            &mut decrypted,
+            KEY,
+        )
+        .unwrap();
+        assert_eq!(decrypted, data);
+    }
+}
+
+#[test]
+fn test_decrypt_stream_invalid() {
+    let mut out = Vec::new();
+    let res = decrypt_stream(
+        Mode::CBC(KEY.to_vec()),
+        Padding::Pkcs7,
+        &[0u8; 17][..],
+        &mut out,
+        KEY,
+    );
+    assert!(res.is_err());
+
+    let mut encrypted = encrypt(Mode::ECB, b"Some Crypto Text", KEY).unwrap();
+    encrypted.truncate(16);
+    let res = decrypt_stream(Mode::ECB, Padding::Pkcs7, &encrypted[..], &mut out, KEY);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_encrypt_padded() {
+    let paddings = [
+        Padding::Pkcs7,
+        Padding::Zero,
+        Padding::Iso7816,
+        Padding::AnsiX923,
+    ];
+    let data = b"Some Crypto Text and something else";
+
+    for padding in paddings {
+        for mode in [
+            || Mode::ECB,
+            || Mode::CBC(KEY.to_vec()),
+            || Mode::PCBC(KEY.to_vec()),
+        ] {
+            let encrypted = encrypt_padded(mode(), padding, data, KEY).unwrap();
+            assert_eq!(encrypted.len(), 48);
+
+            let decrypted = decrypt_padded(mode(), padding, &encrypted, KEY).unwrap();
+            assert_eq!(decrypted, data);
+
+            let mut streamed = Vec::new();
+            encrypt_stream(mode(), padding, &data[..], &mut streamed, KEY).unwrap();
+            assert_eq!(streamed, encrypted);
+
+            let mut decrypted = Vec::new();
+            decrypt_stream(mode(), padding, &encrypted[..], &mut decrypted, KEY).unwrap();
+            assert_eq!(decrypted, data);
+        }
+    }
+}
+
+#[test]
+fn test_encrypt_no_padding() {
+    let data = b"Some Crypto Text";
+    let encrypted = encrypt_padded(Mode::ECB, Padding::None, data, KEY).unwrap();
+    let expected = encrypt(Mode::ECB, data, KEY).unwrap();
+    assert_eq!(encrypted, expected[..16]);
+
+    let decrypted = decrypt_padded(Mode::ECB, Padding::None, &expected, KEY).unwrap();
+    assert_eq!(decrypted[..16], data[..]);
+    assert_eq!(decrypted[16..], [16; 16]);
+
+    assert!(encrypt_padded(Mode::CBC(KEY.to_vec()), Padding::None, b"unaligned", KEY).is_err());
+}
+
+#[test]
+fn test_decrypt_padding_mismatch() {
+    let encrypted = encrypt_padded(Mode::ECB, Padding::Zero, b"Some Crypto", KEY).unwrap();
+    assert!(decrypt_padded(Mode::ECB, Padding::Pkcs7, &encrypted, KEY).is_err());
+    assert!(decrypt_padded(Mode::ECB, Padding::Iso7816, &encrypted, KEY).is_err());
+}
@@ -0,0 +1,161 @@
+//! Bitflipping of CBC and CTR ciphertexts.
+//!
+//! Neither mode protects the integrity of the ciphertext: flipping a bit
+//! of a CBC ciphertext block flips the same bit of the plaintext in the
+//! next block (while garbling the block itself), and in CTR it flips the
+//! same bit of the plaintext directly. Knowing part of the plaintext is
+//! therefore enough to replace it with anything of the same length.
+
+use crate::{Error, Result};
+
+/// Returns the XOR difference that turns `known` into `wanted`.
+pub fn delta(known: &[u8], wanted: &[u8]) -> Result<Vec<u8>> {
+    if known.len() != wanted.len() {
+        return Err(Error::ArgError(format!(
+            "known and wanted plaintext must have the same length: {} != {}",
+            known.len(),
+            wanted.len()
+        )));
+    }
+    Ok(known.iter().zip(wanted).map(|(a, b)| a ^ b).collect())
+}
+
+/// Changes a CBC `ciphertext` so that the plaintext `known`, starting at
+/// byte `offset` of the plaintext, decrypts to `wanted` instead.
+///
+/// The change is made to the preceding ciphertext block, or to the `iv`
+/// if `offset` is in the first block, and the block size is the length
+/// of the IV. Since the preceding block decrypts to garbage afterwards
+/// the plaintext must lie within a single block.
+pub fn cbc_flip(
+    iv: &mut [u8],
+    ciphertext: &mut [u8],
+    offset: usize,
+    known: &[u8],
+    wanted: &[u8],
+) -> Result<()> {
+    let d = delta(known, wanted)?;
+    let size = iv.len();
+    if size == 0 {
+        return Err(Error::ArgError("the IV cannot be empty".to_string()));
+    }
+
+    let end = flip_end(offset, d.len(), ciphertext.len())?;
+    if !d.is_empty() && offset / size != (end - 1) / size {
+        return Err(Error::ArgError(format!(
+            "plaintext at {}..{} spans more than one block",
+            offset, end
+        )));
+    }
+
+    let block = offset / size;
+    let target = if block == 0 {
+        &mut iv[offset..end]
+    } else {
+        &mut ciphertext[offset - size..end - size]
+    };
+    xor_into(target, &d);
+    Ok(())
+}
+
+/// Changes a CTR `ciphertext` so that the plaintext `known`, starting at
+/// byte `offset`, decrypts to `wanted` instead. Any other plaintext is
+/// left intact.
+pub fn ctr_flip(ciphertext: &mut [u8], offset: usize, known: &[u8], wanted: &[u8]) -> Result<()> {
+    let d = delta(known, wanted)?;
+    let end = flip_end(offset, d.len(), ciphertext.len())?;
+
+    xor_into(&mut ciphertext[offset..end], &d);
+    Ok(())
+}
+
+/// Returns the end of a flip of `n` bytes at `offset`, checking that it
+/// lies within a ciphertext of length `len`.
+fn flip_end(offset: usize, n: usize, len: usize) -> Result<usize> {
+    let end = offset.checked_add(n).ok_or_else(|| {
+        Error::ArgError(format!("flip out of range: offset {} is too large", offset))
+    })?;
+    if end > len {
+        return Err(Error::ArgError(format!(
+            "flip out of range: {} is past the end of the ciphertext ({})",
+            end, len
+        )));
+    }
+    Ok(end)
+}
+
+fn xor_into(a: &mut [u8], b: &[u8]) {
+    for (x, y) in a.iter_mut().zip(b) {
+        *x ^= y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{self, Counter, Mode};
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+    const PLAINTEXT: &[u8] = b"comment1=cooking%20MCs;userdata=XadminXtrueX;comment2=%20like";
+
+    #[test]
+    fn test_cbc_flip() {
+        let mut iv = vec![0u8; 16];
+        let mut ciphertext = aes::encrypt(Mode::CBC(iv.clone()), PLAINTEXT, KEY).unwrap();
+
+        cbc_flip(
+            &mut iv,
+            &mut ciphertext,
+            32,
+            b"XadminXtrueX",
+            b";admin=true;",
+        )
+        .unwrap();
+
+        let decrypted = aes::decrypt(Mode::CBC(iv), &ciphertext, KEY).unwrap();
+        assert_eq!(&decrypted[32..44], b";admin=true;");
+        assert_eq!(decrypted[..16], PLAINTEXT[..16]);
+        assert_ne!(decrypted[16..32], PLAINTEXT[16..32]);
+        assert_eq!(decrypted[44..], PLAINTEXT[44..]);
+    }
+
+    #[test]
+    fn test_cbc_flip_first_block() {
+        let mut iv = b"0123456789abcdef".to_vec();
+        let mut ciphertext = aes::encrypt(Mode::CBC(iv.clone()), PLAINTEXT, KEY).unwrap();
+        let original = ciphertext.clone();
+
+        cbc_flip(&mut iv, &mut ciphertext, 9, b"cooking", b"hacking").unwrap();
+        assert_eq!(ciphertext, original);
+
+        let decrypted = aes::decrypt(Mode::CBC(iv), &ciphertext, KEY).unwrap();
+        assert_eq!(&decrypted[..16], b"comment1=hacking");
+        assert_eq!(decrypted[16..], PLAINTEXT[16..]);
+    }
+
+    #[test]
+    fn test_cbc_flip_invalid() {
+        let mut iv = vec![0u8; 16];
+        let mut ciphertext = vec![0u8; 48];
+        assert!(cbc_flip(&mut iv, &mut ciphertext, 14, b"abcd", b"efgh").is_err());
+        assert!(cbc_flip(&mut iv, &mut ciphertext, 46, b"abcd", b"efgh").is_err());
+        assert!(cbc_flip(&mut iv, &mut ciphertext, 0, b"abcd", b"efg").is_err());
+        assert!(cbc_flip(&mut [], &mut ciphertext, 0, b"abcd", b"efgh").is_err());
+        assert!(cbc_flip(&mut iv, &mut ciphertext, usize::MAX, b"abcd", b"efgh").is_err());
+    }
+
+    #[test]
+    fn test_ctr_flip() {
+        let mode = || Mode::CTR(vec![0u8; 8], Counter::LittleEndian64);
+        let mut ciphertext = aes::encrypt(mode(), PLAINTEXT, KEY).unwrap();
+
+        ctr_flip(&mut ciphertext, 32, b"XadminXtrueX", b";admin=true;").unwrap();
+        assert!(ctr_flip(&mut ciphertext, 60, b"ab", b"cd").is_err());
+        assert!(ctr_flip(&mut ciphertext, usize::MAX, b"ab", b"cd").is_err());
+
+        let decrypted = aes::decrypt(mode(), &ciphertext, KEY).unwrap();
+        let mut expected = PLAINTEXT.to_vec();
+        expected[32..44].copy_from_slice(b";admin=true;");
+        assert_eq!(decrypted, expected);
+    }
+}
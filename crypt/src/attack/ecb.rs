@@ -0,0 +1,127 @@
+//! Byte-at-a-time decryption of ECB.
+//!
+//! The oracle encrypts `prefix || input || suffix` with ECB under a fixed
+//! key, where the input is chosen by the attacker and the prefix and
+//! suffix are unknown but the same for every call.
+
+use super::profile::{OracleMode, OracleProfile};
+use super::{block, FILLER};
+use crate::{Error, Result};
+
+/// Recovers the unknown suffix appended by `oracle` to its input. The
+/// block size, the length of any prefix and the length of the suffix
+/// are all detected with `OracleProfile`.
+pub fn ecb_byte_at_a_time<F>(oracle: F) -> Result<Vec<u8>>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    let profile = OracleProfile::detect(&oracle)?;
+    if profile.mode != OracleMode::Ecb {
+        return Err(Error::DataError(format!(
+            "the oracle doesn't use ECB but {:?}",
+            profile.mode
+        )));
+    }
+    let (prefix_len, suffix_len) = match (profile.prefix_len, profile.suffix_len) {
+        (Some(p), Some(s)) => (p, s),
+        _ => {
+            return Err(Error::DataError(
+                "the oracle isn't deterministic".to_string(),
+            ))
+        }
+    };
+    let block_size = profile.block_size;
+
+    // Number of filler bytes that completes the last block of the prefix,
+    // so that everything after it starts on a block boundary.
+    let align = (block_size - prefix_len % block_size) % block_size;
+    let skip = (prefix_len + align) / block_size;
+
+    let mut suffix: Vec<u8> = Vec::with_capacity(suffix_len);
+    while suffix.len() < suffix_len {
+        // Shift the suffix so that the next unknown byte is the last
+        // byte of a block, with every other byte in it already known.
+        let i = suffix.len();
+        let shift = block_size - 1 - i % block_size;
+        let index = skip + i / block_size;
+        let encrypted = oracle(&vec![FILLER; align + shift])?;
+        let target = block(&encrypted, index, block_size)?;
+
+        // The known bytes of that block, i.e. the last block size - 1
+        // bytes of filler followed by the suffix found so far.
+        let mut known = vec![FILLER; block_size - 1];
+        known.extend_from_slice(&suffix);
+        let known = &known[known.len() - (block_size - 1)..];
+
+        let mut input = vec![FILLER; align];
+        input.extend_from_slice(known);
+        input.push(0);
+
+        let mut found = None;
+        for b in 0..=255u8 {
+            input[align + block_size - 1] = b;
+            let encrypted = oracle(&input)?;
+            if block(&encrypted, skip, block_size)? == target {
+                found = Some(b);
+                break;
+            }
+        }
+
+        match found {
+            Some(b) => suffix.push(b),
+            None => {
+                return Err(Error::DataError(format!(
+                    "no byte matched byte {} of the suffix",
+                    i
+                )))
+            }
+        }
+    }
+
+    Ok(suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{self, Mode};
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+    const SUFFIX: &[u8] = b"Rollin' in my 5.0\nWith my rag-top down so my hair can blow\n";
+
+    fn encrypt(mode: Mode, prefix: &[u8], input: &[u8], suffix: &[u8]) -> Result<Vec<u8>> {
+        let mut data = prefix.to_vec();
+        data.extend_from_slice(input);
+        data.extend_from_slice(suffix);
+        aes::encrypt(mode, &data, KEY)
+    }
+
+    #[test]
+    fn test_ecb_byte_at_a_time() {
+        for prefix_len in [0, 1, 7, 15, 16, 17, 33, 40] {
+            let prefix: Vec<u8> = (0..prefix_len as u8).collect();
+            let oracle = |input: &[u8]| encrypt(Mode::ECB, &prefix, input, SUFFIX);
+
+            let suffix = ecb_byte_at_a_time(oracle).unwrap();
+            assert_eq!(suffix, SUFFIX);
+        }
+    }
+
+    #[test]
+    fn test_ecb_byte_at_a_time_filler_in_data() {
+        // Prefix and suffix that look like the filler and input bytes.
+        let prefix = [0, 0, 1, 0, 0];
+        let suffix = [0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        let oracle = |input: &[u8]| encrypt(Mode::ECB, &prefix, input, &suffix);
+
+        assert_eq!(ecb_byte_at_a_time(oracle).unwrap(), suffix);
+    }
+
+    #[test]
+    fn test_ecb_byte_at_a_time_not_ecb() {
+        let iv = vec![0u8; 16];
+        let oracle = |input: &[u8]| encrypt(Mode::CBC(iv.clone()), b"", input, SUFFIX);
+
+        assert!(ecb_byte_at_a_time(oracle).is_err());
+    }
+}
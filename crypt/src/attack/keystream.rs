@@ -0,0 +1,215 @@
+//! Recovery of a keystream used more than once.
+//!
+//! Stream ciphers, and CTR with a fixed nonce, encrypt every message
+//! with the same keystream. Byte `i` of every ciphertext is then the
+//! plaintext XOR:ed with the same key byte, so each column can be broken
+//! as single-byte XOR. Columns where only a few ciphertexts are long
+//! enough are guesses at best and can be fixed using known plaintext.
+
+use crate::{Candidate, Error, Hacker, Result};
+
+/// Returns the most likely keystream for `ciphertexts`, as long as the
+/// longest of them, by breaking each column as single-byte XOR with the
+/// scorer of `hacker`.
+///
+/// The scorer may ignore case, and then a key byte that turns the letters
+/// of a column into other letters of the wrong case can score as well as
+/// or better than the right one. The case most letters have when every
+/// column uses its best key is taken to be the case of the plaintext, and
+/// each column then uses the best key that leaves most of its letters in
+/// that case.
+pub fn recover<T: AsRef<[u8]>>(hacker: &Hacker, ciphertexts: &[T]) -> Vec<u8> {
+    let len = ciphertexts
+        .iter()
+        .map(|c| c.as_ref().len())
+        .max()
+        .unwrap_or(0);
+
+    let columns: Vec<Vec<Candidate>> = (0..len)
+        .map(|i| {
+            let column: Vec<u8> = ciphertexts
+                .iter()
+                .filter_map(|c| c.as_ref().get(i).copied())
+                .collect();
+            hacker.crack_column(&column, 256)
+        })
+        .collect();
+
+    let (upper, lower) = columns
+        .iter()
+        .map(|candidates| count_case(&candidates[0].plaintext))
+        .fold((0, 0), |(u, l), (cu, cl)| (u + cu, l + cl));
+    let upper_case = upper > lower;
+
+    columns
+        .iter()
+        .map(|candidates| {
+            candidates
+                .iter()
+                .find(|c| {
+                    let (upper, lower) = count_case(&c.plaintext);
+                    if upper_case {
+                        upper >= lower
+                    } else {
+                        lower >= upper
+                    }
+                })
+                .unwrap_or(&candidates[0])
+                .key
+        })
+        .collect()
+}
+
+/// Counts the upper and lower case letters in `text`.
+fn count_case(text: &[u8]) -> (usize, usize) {
+    let upper = text.iter().filter(|b| b.is_ascii_uppercase()).count();
+    let lower = text.iter().filter(|b| b.is_ascii_lowercase()).count();
+    (upper, lower)
+}
+
+/// Corrects the `keystream` using the known `plaintext` of `ciphertext`,
+/// starting at byte `offset`.
+pub fn correct(
+    keystream: &mut [u8],
+    ciphertext: &[u8],
+    offset: usize,
+    plaintext: &[u8],
+) -> Result<()> {
+    let end = offset.checked_add(plaintext.len()).ok_or_else(|| {
+        Error::ArgError(format!(
+            "correction out of range: offset {} is too large",
+            offset
+        ))
+    })?;
+    if end > ciphertext.len() || end > keystream.len() {
+        return Err(Error::ArgError(format!(
+            "correction out of range: {} is past the end of the ciphertext ({}) or keystream ({})",
+            end,
+            ciphertext.len(),
+            keystream.len()
+        )));
+    }
+
+    for (i, p) in plaintext.iter().enumerate() {
+        keystream[offset + i] = ciphertext[offset + i] ^ p;
+    }
+    Ok(())
+}
+
+/// Decrypts `ciphertext` with `keystream`. If the keystream is shorter
+/// only that many bytes are decrypted.
+pub fn decrypt(keystream: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    ciphertext
+        .iter()
+        .zip(keystream)
+        .map(|(c, k)| c ^ k)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYSTREAM: &[u8] =
+        b"\x8a\x01\x33\xf0\x5c\x7e\x12\xc4\x99\x00\x6b\xd3\x21\x48\xe5\x0f\x70\xab\x5e\x91";
+
+    fn encrypt(lines: &[&[u8]]) -> Vec<Vec<u8>> {
+        lines.iter().map(|line| decrypt(KEYSTREAM, line)).collect()
+    }
+
+    #[test]
+    fn test_recover() {
+        let lines: [&[u8]; 16] = [
+            b"the quick brown fox",
+            b"jumps over the lazy",
+            b"dog and then it ran",
+            b"into the deep woods",
+            b"where no one would ",
+            b"ever find it again.",
+            b"it was never seen  ",
+            b"by anyone after it.",
+            b"Some say that it is",
+            b"still there, hiding",
+            b"in a hole under the",
+            b"roots of an old oak",
+            b"tree, waiting for a",
+            b"chance to come back",
+            b"out and jump over a",
+            b"dog that is lazier.",
+        ];
+        let ciphertexts = encrypt(&lines);
+
+        let mut keystream = recover(&Hacker::new(), &ciphertexts);
+        assert_eq!(keystream.len(), 19);
+        let right = keystream
+            .iter()
+            .zip(KEYSTREAM)
+            .filter(|(a, b)| a == b)
+            .count();
+        assert!(right >= 14, "only {} correct", right);
+
+        correct(&mut keystream, &ciphertexts[0], 0, lines[0]).unwrap();
+        assert_eq!(keystream, KEYSTREAM[..19]);
+        for (line, c) in lines.iter().zip(&ciphertexts) {
+            assert_eq!(&decrypt(&keystream, c), line);
+        }
+    }
+
+    #[test]
+    fn test_recover_upper_case() {
+        let lines: Vec<Vec<u8>> = [
+            "THE QUICK BROWN FOX JUMPS",
+            "OVER THE LAZY DOG AND RAN",
+            "INTO THE DEEP DARK WOODS,",
+            "WHERE NO ONE WOULD EVER  ",
+            "FIND IT AGAIN. IT WAS NOT",
+            "SEEN BY ANYONE AFTER THAT",
+            "DAY, BUT SOME SAY THAT IT",
+            "IS STILL HIDING IN A HOLE",
+            "UNDER THE ROOTS OF AN OLD",
+            "OAK, WAITING TO COME BACK",
+        ]
+        .iter()
+        .map(|line| line.as_bytes().to_vec())
+        .collect();
+        let keystream: Vec<u8> = KEYSTREAM.iter().cycle().take(25).copied().collect();
+        let encrypt = |lines: &[Vec<u8>]| -> Vec<Vec<u8>> {
+            lines.iter().map(|line| decrypt(&keystream, line)).collect()
+        };
+
+        let count_right = |recovered: &[u8]| {
+            recovered
+                .iter()
+                .zip(&keystream)
+                .filter(|(a, b)| a == b)
+                .count()
+        };
+        let upper = count_right(&recover(&Hacker::new(), &encrypt(&lines)));
+        let lower_lines: Vec<Vec<u8>> = lines.iter().map(|l| l.to_ascii_lowercase()).collect();
+        let lower = count_right(&recover(&Hacker::new(), &encrypt(&lower_lines)));
+        assert!(upper >= 18, "only {} correct", upper);
+        assert_eq!(upper, lower);
+    }
+
+    #[test]
+    fn test_correct() {
+        let ciphertexts = encrypt(&[b"short", b"a longer line"]);
+        let mut keystream = vec![0u8; 13];
+
+        correct(&mut keystream, &ciphertexts[1], 2, b"longer").unwrap();
+        assert_eq!(keystream[2..8], KEYSTREAM[2..8]);
+        assert_eq!(&decrypt(&keystream, &ciphertexts[1])[2..8], b"longer");
+        assert_eq!(keystream[..2], [0, 0]);
+
+        assert!(correct(&mut keystream, &ciphertexts[0], 2, b"orts").is_err());
+        assert!(correct(&mut keystream[..4], &ciphertexts[1], 2, b"lon").is_err());
+        assert!(correct(&mut keystream, &ciphertexts[1], usize::MAX, b"x").is_err());
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let ciphertexts = encrypt(&[b"a plaintext that is long"]);
+        assert_eq!(decrypt(KEYSTREAM, &ciphertexts[0]), b"a plaintext that is ");
+        assert!(recover(&Hacker::new(), &Vec::<Vec<u8>>::new()).is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+//! Length extension against MACs computed as `H(secret || message)` with
+//! a Merkle–Damgård hash.
+//!
+//! The MAC is the state of the hash after `secret || message || glue`,
+//! where the glue is the padding the hash added. Hashing can continue
+//! from there, so the MAC of `message || glue || extension` can be
+//! computed without the secret. Only its length is needed for the glue,
+//! which is why a range of guesses is tried.
+
+use crate::hash::MerkleDamgard;
+use crate::{Error, Result};
+use std::ops::RangeInclusive;
+
+/// A forged message and its MAC, for one guess of the secret length.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Forgery {
+    pub secret_len: usize,
+    /// `message || glue || extension`
+    pub message: Vec<u8>,
+    pub mac: Vec<u8>,
+}
+
+/// Forges the MAC of `message || glue || extension` from the `mac` of
+/// `message`, assuming that the secret is `secret_len` bytes long.
+pub fn extend<H: MerkleDamgard>(
+    mac: &[u8],
+    message: &[u8],
+    secret_len: usize,
+    extension: &[u8],
+) -> Result<Forgery> {
+    let too_long = || {
+        Error::ArgError(format!(
+            "secret length {} and message length {} are too long",
+            secret_len,
+            message.len()
+        ))
+    };
+    let len = secret_len.checked_add(message.len()).ok_or_else(too_long)? as u64;
+    let glue = H::padding(len);
+    let hashed = len.checked_add(glue.len() as u64).ok_or_else(too_long)?;
+
+    let mut hash = H::from_digest(mac, hashed)?;
+    hash.update(extension);
+
+    let mut forged = message.to_vec();
+    forged.extend_from_slice(&glue);
+    forged.extend_from_slice(extension);
+    Ok(Forgery {
+        secret_len,
+        message: forged,
+        mac: hash.finalize(),
+    })
+}
+
+/// Forges one message and MAC for every secret length in `secret_lens`,
+/// one at a time, so that a wide range can be tried until one is accepted.
+pub fn forge<'a, H: MerkleDamgard>(
+    mac: &'a [u8],
+    message: &'a [u8],
+    secret_lens: RangeInclusive<usize>,
+    extension: &'a [u8],
+) -> impl Iterator<Item = Result<Forgery>> + 'a {
+    secret_lens.map(move |secret_len| extend::<H>(mac, message, secret_len, extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{Md4, Sha1};
+
+    const SECRET: &[u8] = b"not so secret";
+    const MESSAGE: &[u8] = b"user=alice;amount=10";
+
+    fn mac<H: MerkleDamgard>(message: &[u8]) -> Vec<u8> {
+        let mut hash = H::new();
+        hash.update(SECRET);
+        hash.update(message);
+        hash.finalize()
+    }
+
+    #[test]
+    fn test_extend() {
+        let forgery = extend::<Sha1>(
+            &mac::<Sha1>(MESSAGE),
+            MESSAGE,
+            SECRET.len(),
+            b";amount=1000",
+        )
+        .unwrap();
+        assert!(forgery.message.starts_with(MESSAGE));
+        assert!(forgery.message.ends_with(b";amount=1000"));
+        assert_eq!(forgery.mac, mac::<Sha1>(&forgery.message));
+    }
+
+    #[test]
+    fn test_forge() {
+        let original = mac::<Sha1>(MESSAGE);
+        let forgeries = forge::<Sha1>(&original, MESSAGE, 0..=32, b";admin=true")
+            .collect::<Result<Vec<Forgery>>>()
+            .unwrap();
+        assert_eq!(forgeries.len(), 33);
+
+        let valid: Vec<usize> = forgeries
+            .iter()
+            .filter(|f| f.mac == mac::<Sha1>(&f.message))
+            .map(|f| f.secret_len)
+            .collect();
+        assert_eq!(valid, [SECRET.len()]);
+    }
+
+    #[test]
+    fn test_md4() {
+        let forgery =
+            extend::<Md4>(&mac::<Md4>(MESSAGE), MESSAGE, SECRET.len(), b";amount=1000").unwrap();
+        assert_eq!(forgery.mac, mac::<Md4>(&forgery.message));
+    }
+
+    #[test]
+    fn test_invalid_mac() {
+        assert!(extend::<Sha1>(b"short", MESSAGE, 8, b"x").is_err());
+        assert!(forge::<Sha1>(b"short", MESSAGE, 0..=8, b"x").all(|f| f.is_err()));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let original = mac::<Md4>(MESSAGE);
+        assert!(extend::<Md4>(&original, MESSAGE, usize::MAX, b"x").is_err());
+        let longest = usize::MAX - MESSAGE.len();
+        assert!(extend::<Md4>(&original, MESSAGE, longest, b"x").is_err());
+        assert!(extend::<Sha1>(&mac::<Sha1>(MESSAGE), MESSAGE, longest - 3, b"x").is_err());
+        let mut forgeries = forge::<Md4>(&original, MESSAGE, usize::MAX - 1..=usize::MAX, b"x");
+        assert!(forgeries.all(|f| f.is_err()));
+    }
+}
@@ -0,0 +1,29 @@
+//! Attacks against the primitives in this crate, written against
+//! black-box oracles so that they can be reused outside the challenges.
+
+pub mod bitflip;
+pub mod ecb;
+pub mod keystream;
+pub mod length_extension;
+pub mod padding_oracle;
+pub mod prng;
+pub mod profile;
+
+pub use ecb::ecb_byte_at_a_time;
+pub use profile::{OracleMode, OracleProfile};
+
+use crate::{Error, Result};
+
+/// Byte used to fill the input given to chosen-plaintext oracles.
+const FILLER: u8 = 0;
+
+/// Returns block `index` of `data`.
+fn block(data: &[u8], index: usize, block_size: usize) -> Result<&[u8]> {
+    match data.get(index * block_size..(index + 1) * block_size) {
+        Some(b) => Ok(b),
+        None => Err(Error::DataError(format!(
+            "ciphertext has no block with index {}",
+            index
+        ))),
+    }
+}
@@ -0,0 +1,155 @@
+//! Attacks against MT19937 seeded with too little entropy.
+//!
+//! A generator seeded with a 16 bit number or the current time can be
+//! found by trying every possible seed and comparing its first outputs
+//! with what was observed.
+
+use crate::gen::Mt19937;
+use rand::RngCore;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Returns the first of `seeds` whose generator starts with `outputs`,
+/// or `None` if `outputs` is empty since every seed would match.
+pub fn find_seed<I>(seeds: I, outputs: &[u32]) -> Option<u32>
+where
+    I: IntoIterator<Item = u32>,
+{
+    if outputs.is_empty() {
+        return None;
+    }
+    seeds.into_iter().find(|seed| {
+        let mut rng = Mt19937::new(*seed);
+        outputs.iter().all(|output| rng.next_u32() == *output)
+    })
+}
+
+/// Returns the first of `seeds` whose generator has the bytes `known`
+/// at `offset` of its byte stream, or `None` if `known` is empty.
+pub fn find_seed_for_bytes<I>(seeds: I, offset: usize, known: &[u8]) -> Option<u32>
+where
+    I: IntoIterator<Item = u32>,
+{
+    if known.is_empty() {
+        return None;
+    }
+    let mut bytes = vec![0u8; offset.checked_add(known.len())?];
+    seeds.into_iter().find(|seed| {
+        Mt19937::new(*seed).fill_bytes(&mut bytes);
+        bytes[offset..] == *known
+    })
+}
+
+/// Recovers the seed of a generator seeded with a unix timestamp
+/// from the last `window` seconds before `now`.
+pub fn recover_time_seed(outputs: &[u32], now: u64, window: u64) -> Option<u32> {
+    find_seed(time_seeds(now, window), outputs)
+}
+
+/// Shortest token `is_time_seeded_token` accepts.
+pub const MIN_TOKEN_LEN: usize = 4;
+
+/// Tells whether `token` is the start of the byte stream of a generator
+/// seeded with a unix timestamp from the last `window` seconds before `now`.
+///
+/// A random token matches one of the `window + 1` seeds by chance with
+/// probability about `(window + 1) / 2^(8 * token.len())`, so tokens
+/// shorter than `MIN_TOKEN_LEN` are never reported as time seeded. At
+/// that length and a window of an hour the chance is below one in a
+/// million.
+pub fn is_time_seeded_token(token: &[u8], now: u64, window: u64) -> bool {
+    token.len() >= MIN_TOKEN_LEN && find_seed_for_bytes(time_seeds(now, window), 0, token).is_some()
+}
+
+/// Recovers the 16 bit seed of the MT19937 stream cipher, see
+/// `gen::mt19937::xor_keystream`, from `known` plaintext at `offset`.
+pub fn break_stream_cipher(ciphertext: &[u8], offset: usize, known: &[u8]) -> Option<u16> {
+    let keystream: Vec<u8> = ciphertext
+        .get(offset..offset.checked_add(known.len())?)?
+        .iter()
+        .zip(known)
+        .map(|(c, p)| c ^ p)
+        .collect();
+    find_seed_for_bytes(0..=u16::MAX as u32, offset, &keystream).map(|seed| seed as u16)
+}
+
+/// Seconds since the unix epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Timestamps from `now` and back `window` seconds, most recent first.
+fn time_seeds(now: u64, window: u64) -> impl Iterator<Item = u32> {
+    (now.saturating_sub(window)..=now).rev().map(|t| t as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::mt19937::xor_keystream;
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_find_seed() {
+        let mut rng = Mt19937::new(31337);
+        let outputs = [rng.next_u32(), rng.next_u32()];
+        assert_eq!(find_seed(0..=u16::MAX as u32, &outputs), Some(31337));
+        assert_eq!(find_seed(0..1000, &outputs), None);
+        assert_eq!(find_seed(0..1000, &[]), None);
+        assert_eq!(find_seed_for_bytes(0..1000, 0, &[]), None);
+    }
+
+    #[test]
+    fn test_recover_time_seed() {
+        let mut rng = Mt19937::new((NOW - 500) as u32);
+        let output = rng.next_u32();
+        assert_eq!(
+            recover_time_seed(&[output], NOW, 1000),
+            Some((NOW - 500) as u32)
+        );
+        assert_eq!(recover_time_seed(&[output], NOW, 100), None);
+        assert_eq!(recover_time_seed(&[], NOW, 10), None);
+    }
+
+    #[test]
+    fn test_is_time_seeded_token() {
+        let mut token = [0u8; 16];
+        Mt19937::new((NOW - 10) as u32).fill_bytes(&mut token);
+        assert!(is_time_seeded_token(&token, NOW, 60));
+        assert!(!is_time_seeded_token(&token, NOW - 20, 60));
+
+        let mut token = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut token);
+        assert!(!is_time_seeded_token(&token, NOW, 60));
+        assert!(!is_time_seeded_token(&[], NOW, 60));
+
+        // Too short to tell, even though it is from a time seed.
+        let mut token = [0u8; MIN_TOKEN_LEN];
+        Mt19937::new((NOW - 10) as u32).fill_bytes(&mut token);
+        assert!(is_time_seeded_token(&token, NOW, 60));
+        assert!(!is_time_seeded_token(&token[..MIN_TOKEN_LEN - 1], NOW, 60));
+    }
+
+    #[test]
+    fn test_break_stream_cipher() {
+        let mut plaintext = b"random prefix".to_vec();
+        plaintext.extend_from_slice(&[b'A'; 14]);
+        let ciphertext = xor_keystream(4242, &plaintext);
+
+        let offset = ciphertext.len() - 14;
+        assert_eq!(
+            break_stream_cipher(&ciphertext, offset, &[b'A'; 14]),
+            Some(4242)
+        );
+        assert_eq!(break_stream_cipher(&ciphertext, offset, &[b'B'; 14]), None);
+        assert_eq!(
+            break_stream_cipher(&ciphertext, offset + 1, &[b'A'; 14]),
+            None
+        );
+        assert_eq!(break_stream_cipher(&ciphertext, 0, &[]), None);
+        assert_eq!(break_stream_cipher(&ciphertext, usize::MAX, b"A"), None);
+    }
+}
@@ -0,0 +1,246 @@
+//! Reconnaissance of chosen-plaintext encryption oracles.
+//!
+//! The oracle is assumed to encrypt `prefix || input || suffix`, where the
+//! input is chosen by the attacker and the prefix and suffix are unknown
+//! but have the same length for every call.
+
+use super::{block, FILLER};
+use crate::{Error, Result};
+
+/// Mode of operation as seen from the outside.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OracleMode {
+    /// Equal plaintext blocks give equal ciphertext blocks.
+    Ecb,
+    /// A padded block mode where equal plaintext blocks are chained,
+    /// like CBC or PCBC.
+    Cbc,
+    /// A keystream XOR:ed with the plaintext, which is reused between
+    /// calls, like CTR or OFB with a fixed nonce.
+    Ctr,
+    /// Any other mode that doesn't pad the plaintext, like CFB or CTR
+    /// with a new nonce for each call.
+    Stream,
+}
+
+/// What can be learned about an oracle only by calling it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OracleProfile {
+    /// Block size of the cipher, or 1 for modes where the ciphertext
+    /// grows one byte at a time.
+    pub block_size: usize,
+    pub mode: OracleMode,
+    /// Length of the prefix, if the oracle is deterministic.
+    pub prefix_len: Option<usize>,
+    /// Length of the suffix, if the oracle is deterministic.
+    pub suffix_len: Option<usize>,
+}
+
+impl OracleProfile {
+    /// Detects the profile of the `oracle`.
+    pub fn detect<F>(oracle: F) -> Result<Self>
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>>,
+    {
+        let (block_size, unknown_len) = detect_sizes(&oracle)?;
+        let deterministic = oracle(&[FILLER])? == oracle(&[FILLER])?;
+
+        let mode = if block_size > 1 {
+            if has_equal_blocks(&oracle, block_size)? {
+                OracleMode::Ecb
+            } else {
+                OracleMode::Cbc
+            }
+        } else if deterministic && is_keystream(&oracle)? {
+            OracleMode::Ctr
+        } else {
+            OracleMode::Stream
+        };
+
+        let (prefix_len, suffix_len) = if deterministic {
+            let prefix_len = detect_prefix_len(&oracle, block_size)?;
+            match unknown_len.checked_sub(prefix_len) {
+                Some(n) => (Some(prefix_len), Some(n)),
+                None => return Err(Error::DataError("inconsistent prefix length".to_string())),
+            }
+        } else {
+            (None, None)
+        };
+
+        Ok(Self {
+            block_size,
+            mode,
+            prefix_len,
+            suffix_len,
+        })
+    }
+}
+
+/// Returns the block size and the combined length of the prefix and suffix.
+///
+/// The ciphertext of a padded mode grows by a whole block once the padded
+/// length passes a block boundary, which happens when the prefix, the
+/// input and the suffix exactly fill a number of blocks. Without padding
+/// it grows with every byte of input.
+fn detect_sizes<F>(oracle: &F) -> Result<(usize, usize)>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    let initial = oracle(&[])?.len();
+    for n in 1..=256 {
+        let len = oracle(&vec![FILLER; n])?.len();
+        if len > initial {
+            let block_size = len - initial;
+            let unknown_len = if block_size == 1 {
+                initial
+            } else {
+                initial - n
+            };
+            return Ok((block_size, unknown_len));
+        }
+    }
+    Err(Error::DataError(
+        "failed to detect the block size of the oracle".to_string(),
+    ))
+}
+
+/// Three blocks of filler always contain two aligned blocks,
+/// which give the same ciphertext in ECB.
+fn has_equal_blocks<F>(oracle: &F, block_size: usize) -> Result<bool>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    let encrypted = oracle(&vec![FILLER; block_size * 3])?;
+    let blocks: Vec<&[u8]> = encrypted.chunks(block_size).collect();
+    Ok(blocks.windows(2).any(|w| w[0] == w[1]))
+}
+
+/// Checks whether changing the input changes the ciphertext by exactly
+/// the same bits, and nothing else, as when XOR:ing with a keystream.
+fn is_keystream<F>(oracle: &F) -> Result<bool>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    let a = oracle(&[0x00; 32])?;
+    let b = oracle(&[0xff; 32])?;
+    let diff: Vec<u8> = a.iter().zip(&b).map(|(x, y)| x ^ y).collect();
+
+    let start = diff.iter().position(|d| *d != 0).unwrap_or(0);
+    let flipped = diff.iter().filter(|d| **d != 0).count();
+    Ok(a.len() == b.len() && flipped == 32 && diff[start..start + 32].iter().all(|d| *d == 0xff))
+}
+
+/// Returns the length of the prefix.
+///
+/// The first byte of the input ends up in the last block of the prefix,
+/// found as the first block that changes with that byte. Filler is then
+/// added in front of it until the block stays the same, meaning the byte
+/// has been pushed into the next block.
+fn detect_prefix_len<F>(oracle: &F, block_size: usize) -> Result<usize>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    let a = oracle(&[0])?;
+    let b = oracle(&[1])?;
+    let last = match a
+        .chunks(block_size)
+        .zip(b.chunks(block_size))
+        .position(|(x, y)| x != y)
+    {
+        Some(i) => i,
+        None => return Err(Error::DataError("the oracle ignores its input".to_string())),
+    };
+
+    let mut input = vec![FILLER; block_size + 1];
+    for n in 1..=block_size {
+        input[n] = 0;
+        let a = oracle(&input[..=n])?;
+        input[n] = 1;
+        let b = oracle(&input[..=n])?;
+        input[n] = FILLER;
+        if block(&a, last, block_size)? == block(&b, last, block_size)? {
+            return Ok((last + 1) * block_size - n);
+        }
+    }
+
+    Err(Error::DataError(
+        "failed to detect the prefix length of the oracle".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{self, Counter, Mode};
+    use crate::gen::random_key;
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+
+    fn encrypt(mode: Mode, prefix: &[u8], input: &[u8], suffix: &[u8]) -> Result<Vec<u8>> {
+        let mut data = prefix.to_vec();
+        data.extend_from_slice(input);
+        data.extend_from_slice(suffix);
+        aes::encrypt(mode, &data, KEY)
+    }
+
+    fn profile(mode: OracleMode, block_size: usize, prefix: usize, suffix: usize) -> OracleProfile {
+        OracleProfile {
+            block_size,
+            mode,
+            prefix_len: Some(prefix),
+            suffix_len: Some(suffix),
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let iv = vec![7u8; 16];
+        for (prefix_len, suffix_len) in [(0, 0), (0, 5), (3, 16), (16, 1), (21, 40)] {
+            let prefix = vec![1u8; prefix_len];
+            let suffix = vec![2u8; suffix_len];
+
+            let ecb = |input: &[u8]| encrypt(Mode::ECB, &prefix, input, &suffix);
+            let expected = profile(OracleMode::Ecb, 16, prefix_len, suffix_len);
+            assert_eq!(OracleProfile::detect(ecb).unwrap(), expected);
+
+            let cbc = |input: &[u8]| encrypt(Mode::CBC(iv.clone()), &prefix, input, &suffix);
+            let expected = profile(OracleMode::Cbc, 16, prefix_len, suffix_len);
+            assert_eq!(OracleProfile::detect(cbc).unwrap(), expected);
+
+            let ctr = |input: &[u8]| {
+                let mode = Mode::CTR(vec![0; 8], Counter::LittleEndian64);
+                encrypt(mode, &prefix, input, &suffix)
+            };
+            let expected = profile(OracleMode::Ctr, 1, prefix_len, suffix_len);
+            assert_eq!(OracleProfile::detect(ctr).unwrap(), expected);
+
+            let cfb = |input: &[u8]| encrypt(Mode::CFB8(iv.clone()), &prefix, input, &suffix);
+            let expected = profile(OracleMode::Stream, 1, prefix_len, suffix_len);
+            assert_eq!(OracleProfile::detect(cfb).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_detect_random_iv() {
+        let cbc = |input: &[u8]| encrypt(Mode::CBC(random_key()), b"prefix", input, b"suffix");
+        let profile = OracleProfile::detect(cbc).unwrap();
+        assert_eq!(profile.mode, OracleMode::Cbc);
+        assert_eq!(profile.block_size, 16);
+        assert_eq!(profile.prefix_len, None);
+        assert_eq!(profile.suffix_len, None);
+
+        let ctr = |input: &[u8]| {
+            let mode = Mode::CTR(random_key(), Counter::BigEndian128);
+            encrypt(mode, b"prefix", input, b"suffix")
+        };
+        let profile = OracleProfile::detect(ctr).unwrap();
+        assert_eq!(profile.mode, OracleMode::Stream);
+        assert_eq!(profile.block_size, 1);
+    }
+
+    #[test]
+    fn test_detect_ignores_input() {
+        let oracle = |_: &[u8]| Ok(vec![0u8; 16]);
+        assert!(OracleProfile::detect(oracle).is_err());
+    }
+}
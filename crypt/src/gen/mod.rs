@@ -1,19 +1,202 @@
-use rand::{Rng, RngCore};
+//! Random keys and data.
+//!
+//! Everything random is drawn from a `KeySource`, which is any random
+//! number generator. The functions without a source use the default
+//! source of the current thread. It draws from the operating system's
+//! entropy, unless the `CRYPT_SEED` environment variable is set, which
+//! seeds it so that a run can be replayed.
+
+pub mod mt19937;
+
+pub use mt19937::{Mt19937, Mt19937_64};
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::env;
 use std::ops::Range;
 
+use crate::{Error, Result};
+
+/// Environment variable with the seed of the default key source.
+pub const SEED_VAR: &str = "CRYPT_SEED";
+
+/// Source of random keys, IVs and data.
+pub trait KeySource: RngCore {
+    /// Returns a random 128 bit key.
+    fn random_key(&mut self) -> Vec<u8> {
+        let mut data = vec![0u8; 16];
+        self.fill_bytes(&mut data);
+        data
+    }
+
+    /// Returns random bytes with a length in `r`.
+    fn random_data(&mut self, r: Range<usize>) -> Vec<u8> {
+        let len = self.gen_range(r);
+        let mut data = vec![0u8; len];
+        self.fill_bytes(&mut data);
+        data
+    }
+}
+
+impl<R: RngCore + ?Sized> KeySource for R {}
+
+/// Deterministic key source, which gives the same keys for the same seed.
+pub struct SeededSource {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SeededSource {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Seeded with a random seed, which can be read with `seed` to
+    /// replay the run. Only use it where replaying matters more than
+    /// the 64 bits of the seed, such as in tests.
+    pub fn random() -> Self {
+        Self::new(rand::thread_rng().gen())
+    }
+
+    /// Seeded from `CRYPT_SEED`, or `None` if it isn't set.
+    pub fn from_env() -> Result<Option<Self>> {
+        match env::var(SEED_VAR) {
+            Ok(seed) => seed
+                .trim()
+                .parse()
+                .map(|seed| Some(Self::new(seed)))
+                .map_err(|_| {
+                    Error::ArgError(format!(
+                        "{} must be an unsigned 64 bit integer but was {}",
+                        SEED_VAR, seed
+                    ))
+                }),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// The seed, which recreates the source when passed to `new`
+    /// or set as `CRYPT_SEED`.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for SeededSource {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+thread_local! {
+    // Seeded from `CRYPT_SEED` if it is set, else `None` to use `thread_rng`.
+    static DEFAULT: RefCell<Option<SeededSource>> = RefCell::new(
+        SeededSource::from_env().unwrap_or_else(|err| {
+            eprintln!("ignoring {}: {}", SEED_VAR, err);
+            None
+        })
+    );
+}
+
+/// The default key source of the current thread, which uses the entropy
+/// of the operating system unless `CRYPT_SEED` is set. An invalid seed is
+/// reported on stderr and ignored.
+pub struct DefaultSource;
+
+impl DefaultSource {
+    /// The seed of the default source of the current thread,
+    /// or `None` if it isn't seeded.
+    pub fn seed() -> Option<u64> {
+        DEFAULT.with(|source| source.borrow().as_ref().map(|s| s.seed()))
+    }
+
+    fn with<T>(f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        DEFAULT.with(|source| match source.borrow_mut().as_mut() {
+            Some(seeded) => f(seeded),
+            None => f(&mut rand::thread_rng()),
+        })
+    }
+}
+
+impl RngCore for DefaultSource {
+    fn next_u32(&mut self) -> u32 {
+        Self::with(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        Self::with(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        Self::with(|rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        Self::with(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+/// Returns a random 128 bit key from the default source.
 pub fn random_key() -> Vec<u8> {
-    let mut data = [0u8; 16];
-    rand::thread_rng().fill_bytes(&mut data);
-    data.to_vec()
+    DefaultSource.random_key()
 }
 
+/// Returns random bytes with a length in `r` from the default source.
 pub fn random_data(r: Range<usize>) -> Vec<u8> {
-    let mut data = Vec::new();
-    let mut rng = rand::thread_rng();
-    let a: usize = rng.gen_range(r);
-    for _ in 0..a {
-        let n: u8 = rng.gen();
-        data.push(n);
-    }
-    data
+    DefaultSource.random_data(r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_source() {
+        let mut a = SeededSource::new(42);
+        let mut b = SeededSource::new(42);
+        assert_eq!(a.seed(), 42);
+        assert_eq!(a.random_key(), b.random_key());
+        assert_eq!(a.random_data(0..100), b.random_data(0..100));
+        assert_ne!(a.random_key(), SeededSource::new(43).random_key());
+
+        let c = SeededSource::random();
+        assert_eq!(
+            SeededSource::new(c.seed()).random_key(),
+            SeededSource::new(c.seed()).random_key()
+        );
+    }
+
+    #[test]
+    fn test_random_data() {
+        let mut source = SeededSource::new(1);
+        for _ in 0..20 {
+            let data = source.random_data(5..11);
+            assert!((5..11).contains(&data.len()));
+        }
+        assert_eq!(random_key().len(), 16);
+        assert_ne!(random_key(), random_key());
+    }
+
+    #[test]
+    fn test_key_source_object() {
+        let mut seeded = SeededSource::new(7);
+        let source: &mut dyn KeySource = &mut seeded;
+        assert_eq!(source.random_key(), SeededSource::new(7).random_key());
+    }
 }
@@ -0,0 +1,341 @@
+//! The Mersenne Twister pseudo random number generators MT19937 and
+//! MT19937-64.
+//!
+//! The outputs are a tempered copy of the internal state, and the
+//! tempering can be inverted, so a generator can be cloned from as many
+//! consecutive outputs as it has words of state.
+
+use crate::{Error, Result};
+use rand::RngCore;
+
+const N: usize = 624;
+const M: usize = 397;
+const MATRIX_A: u32 = 0x9908b0df;
+const UPPER_MASK: u32 = 0x80000000;
+const LOWER_MASK: u32 = 0x7fffffff;
+
+const N_64: usize = 312;
+const M_64: usize = 156;
+const MATRIX_A_64: u64 = 0xb5026f5aa96619e9;
+const UPPER_MASK_64: u64 = 0xffffffff80000000;
+const LOWER_MASK_64: u64 = 0x7fffffff;
+
+/// The 32 bit Mersenne Twister.
+#[derive(Clone)]
+pub struct Mt19937 {
+    state: [u32; N],
+    index: usize,
+}
+
+impl Mt19937 {
+    /// Number of outputs needed to clone a generator.
+    pub const STATE_LEN: usize = N;
+
+    pub fn new(seed: u32) -> Self {
+        let mut state = [0u32; N];
+        state[0] = seed;
+        for i in 1..N {
+            let prev = state[i - 1];
+            state[i] = 1812433253u32
+                .wrapping_mul(prev ^ (prev >> 30))
+                .wrapping_add(i as u32);
+        }
+        Self { state, index: N }
+    }
+
+    /// Clones the generator that returned `outputs`, which must be
+    /// `STATE_LEN` consecutive outputs. The clone continues where the
+    /// outputs end.
+    pub fn from_outputs(outputs: &[u32]) -> Result<Self> {
+        check_outputs_len(outputs.len(), N)?;
+        let mut state = [0u32; N];
+        for (s, output) in state.iter_mut().zip(outputs) {
+            *s = untemper(*output);
+        }
+        Ok(Self { state, index: N })
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let y = self.state[self.index];
+        self.index += 1;
+        temper(y)
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % N] & LOWER_MASK);
+            let mut next = y >> 1;
+            if y & 1 != 0 {
+                next ^= MATRIX_A;
+            }
+            self.state[i] = self.state[(i + M) % N] ^ next;
+        }
+        self.index = 0;
+    }
+}
+
+impl RngCore for Mt19937 {
+    fn next_u32(&mut self) -> u32 {
+        Mt19937::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = Mt19937::next_u32(self) as u64;
+        let high = Mt19937::next_u32(self) as u64;
+        (high << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = Mt19937::next_u32(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// The 64 bit Mersenne Twister.
+#[derive(Clone)]
+pub struct Mt19937_64 {
+    state: [u64; N_64],
+    index: usize,
+}
+
+impl Mt19937_64 {
+    /// Number of outputs needed to clone a generator.
+    pub const STATE_LEN: usize = N_64;
+
+    pub fn new(seed: u64) -> Self {
+        let mut state = [0u64; N_64];
+        state[0] = seed;
+        for i in 1..N_64 {
+            let prev = state[i - 1];
+            state[i] = 6364136223846793005u64
+                .wrapping_mul(prev ^ (prev >> 62))
+                .wrapping_add(i as u64);
+        }
+        Self { state, index: N_64 }
+    }
+
+    /// Clones the generator that returned `outputs`, which must be
+    /// `STATE_LEN` consecutive outputs. The clone continues where the
+    /// outputs end.
+    pub fn from_outputs(outputs: &[u64]) -> Result<Self> {
+        check_outputs_len(outputs.len(), N_64)?;
+        let mut state = [0u64; N_64];
+        for (s, output) in state.iter_mut().zip(outputs) {
+            *s = untemper_64(*output);
+        }
+        Ok(Self { state, index: N_64 })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        if self.index >= N_64 {
+            self.twist();
+        }
+        let y = self.state[self.index];
+        self.index += 1;
+        temper_64(y)
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N_64 {
+            let y = (self.state[i] & UPPER_MASK_64) | (self.state[(i + 1) % N_64] & LOWER_MASK_64);
+            let mut next = y >> 1;
+            if y & 1 != 0 {
+                next ^= MATRIX_A_64;
+            }
+            self.state[i] = self.state[(i + M_64) % N_64] ^ next;
+        }
+        self.index = 0;
+    }
+}
+
+impl RngCore for Mt19937_64 {
+    fn next_u32(&mut self) -> u32 {
+        Mt19937_64::next_u64(self) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        Mt19937_64::next_u64(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = Mt19937_64::next_u64(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Encrypts or decrypts `data` with a stream cipher where the keystream
+/// is the output of MT19937 seeded with `seed`, as little-endian bytes.
+pub fn xor_keystream(seed: u32, data: &[u8]) -> Vec<u8> {
+    let mut keystream = vec![0u8; data.len()];
+    Mt19937::new(seed).fill_bytes(&mut keystream);
+    data.iter().zip(keystream).map(|(d, k)| d ^ k).collect()
+}
+
+/// Tempering of MT19937, which turns a word of state into an output.
+pub fn temper(y: u32) -> u32 {
+    let y = y ^ (y >> 11);
+    let y = y ^ ((y << 7) & 0x9d2c5680);
+    let y = y ^ ((y << 15) & 0xefc60000);
+    y ^ (y >> 18)
+}
+
+/// Inverse of `temper`, which turns an output into a word of state.
+pub fn untemper(y: u32) -> u32 {
+    let y = undo_right(y as u64, 18);
+    let y = undo_left(y, 15, 0xefc60000);
+    let y = undo_left(y, 7, 0x9d2c5680);
+    undo_right(y, 11) as u32
+}
+
+/// Tempering of MT19937-64, which turns a word of state into an output.
+pub fn temper_64(y: u64) -> u64 {
+    let y = y ^ ((y >> 29) & 0x5555555555555555);
+    let y = y ^ ((y << 17) & 0x71d67fffeda60000);
+    let y = y ^ ((y << 37) & 0xfff7eee000000000);
+    y ^ (y >> 43)
+}
+
+/// Inverse of `temper_64`, which turns an output into a word of state.
+pub fn untemper_64(y: u64) -> u64 {
+    let y = undo_right(y, 43);
+    let y = undo_left(y, 37, 0xfff7eee000000000);
+    let y = undo_left(y, 17, 0x71d67fffeda60000);
+    undo_right_masked(y, 29, 0x5555555555555555)
+}
+
+/// Inverts `y ^= y >> shift`.
+fn undo_right(y: u64, shift: u32) -> u64 {
+    undo_right_masked(y, shift, u64::MAX)
+}
+
+/// Inverts `y ^= (y >> shift) & mask`. Every round recovers
+/// another `shift` bits, starting from the most significant.
+fn undo_right_masked(y: u64, shift: u32, mask: u64) -> u64 {
+    let mut x = y;
+    for _ in 0..64 / shift {
+        x = y ^ ((x >> shift) & mask);
+    }
+    x
+}
+
+/// Inverts `y ^= (y << shift) & mask`. Every round recovers
+/// another `shift` bits, starting from the least significant.
+fn undo_left(y: u64, shift: u32, mask: u64) -> u64 {
+    let mut x = y;
+    for _ in 0..64 / shift {
+        x = y ^ ((x << shift) & mask);
+    }
+    x
+}
+
+fn check_outputs_len(len: usize, expected: usize) -> Result<()> {
+    if len == expected {
+        Ok(())
+    } else {
+        Err(Error::ArgError(format!(
+            "invalid number of outputs: must be {} but was {}",
+            expected, len
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mt19937() {
+        let mut rng = Mt19937::new(5489);
+        let first: Vec<u32> = (0..5).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            first,
+            [3499211612, 581869302, 3890346734, 3586334585, 545404204]
+        );
+
+        // The 10000th output is given by the C++ standard.
+        let mut rng = Mt19937::new(5489);
+        let last = (0..10000).map(|_| rng.next_u32()).last();
+        assert_eq!(last, Some(4123659995));
+    }
+
+    #[test]
+    fn test_mt19937_64() {
+        let mut rng = Mt19937_64::new(5489);
+        assert_eq!(rng.next_u64(), 14514284786278117030);
+
+        let mut rng = Mt19937_64::new(5489);
+        let last = (0..10000).map(|_| rng.next_u64()).last();
+        assert_eq!(last, Some(9981545732273789042));
+    }
+
+    #[test]
+    fn test_untemper() {
+        for y in [0, 1, 0x80000000, 0xdeadbeef, u32::MAX, 123456789] {
+            assert_eq!(untemper(temper(y)), y);
+        }
+        for y in [0, 1, 0x8000000000000000, 0xdeadbeefcafebabe, u64::MAX] {
+            assert_eq!(untemper_64(temper_64(y)), y);
+        }
+    }
+
+    #[test]
+    fn test_from_outputs() -> Result<()> {
+        let mut rng = Mt19937::new(1234);
+        // Start in the middle of the state, to not line up with a twist.
+        for _ in 0..100 {
+            rng.next_u32();
+        }
+        let outputs: Vec<u32> = (0..Mt19937::STATE_LEN).map(|_| rng.next_u32()).collect();
+        let mut clone = Mt19937::from_outputs(&outputs)?;
+        for _ in 0..2000 {
+            assert_eq!(clone.next_u32(), rng.next_u32());
+        }
+
+        let mut rng = Mt19937_64::new(1234);
+        let outputs: Vec<u64> = (0..Mt19937_64::STATE_LEN).map(|_| rng.next_u64()).collect();
+        let mut clone = Mt19937_64::from_outputs(&outputs)?;
+        for _ in 0..1000 {
+            assert_eq!(clone.next_u64(), rng.next_u64());
+        }
+
+        assert!(Mt19937::from_outputs(
+            &outputs[..10].iter().map(|o| *o as u32).collect::<Vec<_>>()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rng_core() {
+        let mut rng = Mt19937::new(5489);
+        let mut bytes = [0u8; 6];
+        rng.fill_bytes(&mut bytes);
+        assert_eq!(bytes[..4], 3499211612u32.to_le_bytes());
+        assert_eq!(bytes[4..], 581869302u32.to_le_bytes()[..2]);
+    }
+
+    #[test]
+    fn test_xor_keystream() {
+        let ciphertext = xor_keystream(1234, b"attack at dawn");
+        assert_ne!(ciphertext, b"attack at dawn");
+        assert_eq!(xor_keystream(1234, &ciphertext), b"attack at dawn");
+        assert_ne!(xor_keystream(1235, &ciphertext), b"attack at dawn");
+    }
+}
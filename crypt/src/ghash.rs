@@ -0,0 +1,170 @@
+use std::ops::{Add, Mul};
+
+/// Block size of GHASH in bytes.
+pub const BLOCK_SIZE: usize = 16;
+
+/// The reduction polynomial x^128 + x^7 + x^2 + x + 1, in GCM bit order.
+const R: u128 = 0xe1 << 120;
+
+/// An element of GF(2^128) as used by GCM.
+///
+/// GCM uses a "reflected" bit order: the most significant bit of the
+/// first byte is the coefficient of x^0. The element is stored as the
+/// big-endian interpretation of its 16 bytes, so the most significant
+/// bit of the `u128` is the coefficient of x^0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Gf128(pub u128);
+
+impl Gf128 {
+    pub const ZERO: Gf128 = Gf128(0);
+    pub const ONE: Gf128 = Gf128(1 << 127);
+
+    /// Interprets up to 16 bytes as an element. Shorter input is zero padded,
+    /// which is how GHASH treats a partial final block.
+    pub fn from_bytes(b: &[u8]) -> Self {
+        let mut block = [0u8; BLOCK_SIZE];
+        let n = b.len().min(BLOCK_SIZE);
+        block[..n].copy_from_slice(&b[..n]);
+        Gf128(u128::from_be_bytes(block))
+    }
+
+    pub fn to_bytes(self) -> [u8; BLOCK_SIZE] {
+        self.0.to_be_bytes()
+    }
+
+    /// Raises the element to the power `n` by square-and-multiply.
+    pub fn pow(self, mut n: u128) -> Self {
+        let mut result = Gf128::ONE;
+        let mut base = self;
+        while n != 0 {
+            if n & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            n >>= 1;
+        }
+        result
+    }
+
+    /// Multiplicative inverse, i.e. a^(2^128 - 2). Zero has no inverse.
+    pub fn inverse(self) -> Option<Self> {
+        if self == Gf128::ZERO {
+            None
+        } else {
+            Some(self.pow(u128::MAX - 1))
+        }
+    }
+}
+
+impl Add for Gf128 {
+    type Output = Gf128;
+
+    /// Addition (and subtraction) in a field of characteristic 2 is XOR.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: Gf128) -> Gf128 {
+        Gf128(self.0 ^ other.0)
+    }
+}
+
+impl Mul for Gf128 {
+    type Output = Gf128;
+
+    /// Multiplication according to NIST SP 800-38D, algorithm 1.
+    fn mul(self, other: Gf128) -> Gf128 {
+        let mut z = 0u128;
+        let mut v = other.0;
+        for i in 0..128 {
+            if self.0 & (1 << (127 - i)) != 0 {
+                z ^= v;
+            }
+            v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+        }
+        Gf128(z)
+    }
+}
+
+/// Returns the blocks GHASH is evaluated over: the zero padded `aad`,
+/// the zero padded `ciphertext` and finally the block with their bit lengths.
+///
+/// GHASH is the polynomial with these coefficients evaluated at the
+/// hash key H, with the first block as the coefficient of the highest
+/// power of H. Comparing the coefficients of two messages authenticated
+/// with the same key and nonce is the starting point of the forbidden attack.
+pub fn coefficients(aad: &[u8], ciphertext: &[u8]) -> Vec<Gf128> {
+    let mut blocks: Vec<Gf128> = aad
+        .chunks(BLOCK_SIZE)
+        .chain(ciphertext.chunks(BLOCK_SIZE))
+        .map(Gf128::from_bytes)
+        .collect();
+
+    let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+    blocks.push(Gf128(lengths));
+    blocks
+}
+
+/// GHASH of `aad` and `ciphertext` under the hash key `h`.
+pub fn ghash(h: Gf128, aad: &[u8], ciphertext: &[u8]) -> Gf128 {
+    coefficients(aad, ciphertext)
+        .into_iter()
+        .fold(Gf128::ZERO, |y, block| (y + block) * h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(s: &str) -> Gf128 {
+        Gf128(u128::from_str_radix(s, 16).unwrap())
+    }
+
+    #[test]
+    fn test_mul() {
+        // NIST GCM test case 2: H * C
+        let h = element("66e94bd4ef8a2c3b884cfa59ca342b2e");
+        let c = element("0388dace60b6a392f328c2b971b2fe78");
+        assert_eq!(h * c, element("5e2ec746917062882c85b0685353deb7"));
+    }
+
+    #[test]
+    fn test_mul_identity() {
+        let a = element("66e94bd4ef8a2c3b884cfa59ca342b2e");
+        assert_eq!(a * Gf128::ONE, a);
+        assert_eq!(Gf128::ONE * a, a);
+        assert_eq!(a * Gf128::ZERO, Gf128::ZERO);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let a = element("0388dace60b6a392f328c2b971b2fe78");
+        let inv = a.inverse().unwrap();
+        assert_eq!(a * inv, Gf128::ONE);
+        assert_eq!(Gf128::ZERO.inverse(), None);
+    }
+
+    #[test]
+    fn test_pow() {
+        let a = element("66e94bd4ef8a2c3b884cfa59ca342b2e");
+        assert_eq!(a.pow(0), Gf128::ONE);
+        assert_eq!(a.pow(3), a * a * a);
+    }
+
+    #[test]
+    fn test_ghash() {
+        // NIST GCM test case 2
+        let h = element("66e94bd4ef8a2c3b884cfa59ca342b2e");
+        let c = 0x0388dace60b6a392f328c2b971b2fe78u128.to_be_bytes();
+        assert_eq!(
+            ghash(h, &[], &c),
+            element("f38cbb1ad69223dcc3457ae5b6b0f885")
+        );
+    }
+
+    #[test]
+    fn test_coefficients() {
+        let blocks = coefficients(&[1; 20], &[2; 3]);
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[1].to_bytes()[..4], [1, 1, 1, 1]);
+        assert_eq!(blocks[1].to_bytes()[4..], [0; 12]);
+        assert_eq!(blocks[3], Gf128((160 << 64) | 24));
+    }
+}
@@ -0,0 +1,252 @@
+//! MD4 as specified in RFC 1320.
+
+use super::state::{self, Endian, HashState};
+use crate::Result;
+
+/// Block size of MD4 in bytes.
+pub const BLOCK_SIZE: usize = state::BLOCK_SIZE;
+
+/// Digest size of MD4 in bytes.
+pub const DIGEST_SIZE: usize = 16;
+
+/// The registers before anything is hashed.
+pub const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+/// Incremental MD4, with its registers exposed.
+#[derive(Clone, Debug)]
+pub struct Md4 {
+    inner: HashState<4>,
+}
+
+impl Md4 {
+    pub fn new() -> Self {
+        Self {
+            inner: HashState::new(INITIAL_STATE, Endian::Little, compress),
+        }
+    }
+
+    /// Continues from `state` as if `len` bytes, padding included,
+    /// had already been hashed. `len` must be a multiple of the block size.
+    pub fn from_state(state: [u32; 4], len: u64) -> Result<Self> {
+        Ok(Self {
+            inner: HashState::new(state, Endian::Little, compress).with_len(len)?,
+        })
+    }
+
+    /// Hashes `data` in one go.
+    pub fn digest(data: &[u8]) -> Vec<u8> {
+        let mut md4 = Self::new();
+        md4.update(data);
+        md4.finalize()
+    }
+
+    /// The current registers.
+    pub fn state(&self) -> [u32; 4] {
+        self.inner.state()
+    }
+
+    /// Number of bytes hashed so far.
+    pub fn len(&self) -> u64 {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data)
+    }
+
+    /// Pads the input and returns the digest.
+    pub fn finalize(self) -> Vec<u8> {
+        self.inner.finalize()
+    }
+}
+
+impl Default for Md4 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The padding appended to a message of `len` bytes before it is hashed.
+/// Same as for SHA-1, except that the length is little-endian.
+pub fn padding(len: u64) -> Vec<u8> {
+    state::padding(len, Endian::Little)
+}
+
+/// Splits a digest into the registers that produced it.
+pub fn digest_to_state(digest: &[u8]) -> Result<[u32; 4]> {
+    state::digest_to_state(digest, Endian::Little)
+}
+
+/// Joins the registers into a digest.
+pub fn state_to_digest(state: &[u32; 4]) -> Vec<u8> {
+    state::state_to_digest(state, Endian::Little)
+}
+
+/// The MD4 compression function, which mixes one
+/// block of `BLOCK_SIZE` bytes into `state`.
+pub fn compress(state: &mut [u32; 4], block: &[u8]) {
+    assert_eq!(block.len(), BLOCK_SIZE, "MD4 block must be 64 bytes");
+
+    let mut x = [0u32; 16];
+    for (xi, word) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *xi = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+
+    let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+    let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+    let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+    let [mut a, mut b, mut c, mut d] = *state;
+
+    // Round 1
+    for &i in &[0, 4, 8, 12] {
+        a = a.wrapping_add(f(b, c, d)).wrapping_add(x[i]).rotate_left(3);
+        d = d
+            .wrapping_add(f(a, b, c))
+            .wrapping_add(x[i + 1])
+            .rotate_left(7);
+        c = c
+            .wrapping_add(f(d, a, b))
+            .wrapping_add(x[i + 2])
+            .rotate_left(11);
+        b = b
+            .wrapping_add(f(c, d, a))
+            .wrapping_add(x[i + 3])
+            .rotate_left(19);
+    }
+
+    // Round 2
+    const K2: u32 = 0x5a827999;
+    for i in 0..4 {
+        a = a
+            .wrapping_add(g(b, c, d))
+            .wrapping_add(x[i])
+            .wrapping_add(K2)
+            .rotate_left(3);
+        d = d
+            .wrapping_add(g(a, b, c))
+            .wrapping_add(x[i + 4])
+            .wrapping_add(K2)
+            .rotate_left(5);
+        c = c
+            .wrapping_add(g(d, a, b))
+            .wrapping_add(x[i + 8])
+            .wrapping_add(K2)
+            .rotate_left(9);
+        b = b
+            .wrapping_add(g(c, d, a))
+            .wrapping_add(x[i + 12])
+            .wrapping_add(K2)
+            .rotate_left(13);
+    }
+
+    // Round 3
+    const K3: u32 = 0x6ed9eba1;
+    for &i in &[0, 2, 1, 3] {
+        a = a
+            .wrapping_add(h(b, c, d))
+            .wrapping_add(x[i])
+            .wrapping_add(K3)
+            .rotate_left(3);
+        d = d
+            .wrapping_add(h(a, b, c))
+            .wrapping_add(x[i + 8])
+            .wrapping_add(K3)
+            .rotate_left(9);
+        c = c
+            .wrapping_add(h(d, a, b))
+            .wrapping_add(x[i + 4])
+            .wrapping_add(K3)
+            .rotate_left(11);
+        b = b
+            .wrapping_add(h(c, d, a))
+            .wrapping_add(x[i + 12])
+            .wrapping_add(K3)
+            .rotate_left(15);
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_vectors() {
+        // From RFC 1320
+        let vectors: [(&[u8], &str); 7] = [
+            (b"", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            (b"a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            (b"abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            (b"message digest", "d9130a8164549fe818874806e1c7014b"),
+            (
+                b"abcdefghijklmnopqrstuvwxyz",
+                "d79e1c308aa5bbcdeea8ed63df412da9",
+            ),
+            (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "043f8582f241db351ce627e153e7f0e4",
+            ),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
+        ];
+        for (data, expected) in vectors {
+            assert_eq!(hex(&Md4::digest(data)), expected);
+        }
+    }
+
+    #[test]
+    fn test_update_in_parts() {
+        let data = [0xa5; 300];
+        let expected = Md4::digest(&data);
+        for split in [0, 1, 63, 64, 100, 299] {
+            let mut md4 = Md4::new();
+            md4.update(&data[..split]);
+            md4.update(&data[split..]);
+            assert_eq!(md4.len(), 300);
+            assert_eq!(md4.finalize(), expected);
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        for len in 0..200u64 {
+            let padding = padding(len);
+            assert_eq!((len as usize + padding.len()) % BLOCK_SIZE, 0);
+            assert_eq!(padding[padding.len() - 8..], (len * 8).to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_from_state() {
+        let message = b"message digest";
+        let digest = Md4::digest(message);
+        let state = digest_to_state(&digest).unwrap();
+        assert_eq!(state_to_digest(&state), digest);
+
+        let glue = padding(message.len() as u64);
+        let mut md4 = Md4::from_state(state, (message.len() + glue.len()) as u64).unwrap();
+        md4.update(b" extended");
+
+        let mut forged = message.to_vec();
+        forged.extend_from_slice(&glue);
+        forged.extend_from_slice(b" extended");
+        assert_eq!(md4.finalize(), Md4::digest(&forged));
+
+        assert!(Md4::from_state(state, 1).is_err());
+        assert!(digest_to_state(&digest[..15]).is_err());
+    }
+}
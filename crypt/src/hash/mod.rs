@@ -0,0 +1,95 @@
+//! Hash functions implemented in this crate rather than taken from
+//! OpenSSL, so that their internal state can be read and set. That makes
+//! it possible to continue hashing from a digest, as in a length
+//! extension attack.
+
+pub mod md4;
+pub mod sha1;
+mod state;
+
+pub use md4::Md4;
+pub use sha1::Sha1;
+
+use crate::Result;
+
+/// A Merkle–Damgård hash function: the message is padded to a whole
+/// number of blocks, which are mixed into the state one at a time, and
+/// the digest is the final state. Hashing can therefore continue from a
+/// digest, as long as the length of what produced it is known.
+pub trait MerkleDamgard: Sized {
+    /// Block size in bytes.
+    const BLOCK_SIZE: usize;
+    /// Digest size in bytes.
+    const DIGEST_SIZE: usize;
+
+    fn new() -> Self;
+
+    /// Continues from the state in `digest` as if `len` bytes, padding
+    /// included, had already been hashed.
+    fn from_digest(digest: &[u8], len: u64) -> Result<Self>;
+
+    fn update(&mut self, data: &[u8]);
+
+    /// Pads the input and returns the digest.
+    fn finalize(self) -> Vec<u8>;
+
+    /// The padding appended to a message of `len` bytes before it is hashed.
+    fn padding(len: u64) -> Vec<u8>;
+
+    /// Hashes `data` in one go.
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hash = Self::new();
+        hash.update(data);
+        hash.finalize()
+    }
+}
+
+impl MerkleDamgard for Sha1 {
+    const BLOCK_SIZE: usize = sha1::BLOCK_SIZE;
+    const DIGEST_SIZE: usize = sha1::DIGEST_SIZE;
+
+    fn new() -> Self {
+        Sha1::new()
+    }
+
+    fn from_digest(digest: &[u8], len: u64) -> Result<Self> {
+        Sha1::from_state(sha1::digest_to_state(digest)?, len)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha1::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Sha1::finalize(self)
+    }
+
+    fn padding(len: u64) -> Vec<u8> {
+        sha1::padding(len)
+    }
+}
+
+impl MerkleDamgard for Md4 {
+    const BLOCK_SIZE: usize = md4::BLOCK_SIZE;
+    const DIGEST_SIZE: usize = md4::DIGEST_SIZE;
+
+    fn new() -> Self {
+        Md4::new()
+    }
+
+    fn from_digest(digest: &[u8], len: u64) -> Result<Self> {
+        Md4::from_state(md4::digest_to_state(digest)?, len)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Md4::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        Md4::finalize(self)
+    }
+
+    fn padding(len: u64) -> Vec<u8> {
+        md4::padding(len)
+    }
+}
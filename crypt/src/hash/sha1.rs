@@ -0,0 +1,225 @@
+//! SHA-1 as specified in FIPS 180-4.
+
+use super::state::{self, Endian, HashState};
+use crate::Result;
+
+/// Block size of SHA-1 in bytes.
+pub const BLOCK_SIZE: usize = state::BLOCK_SIZE;
+
+/// Digest size of SHA-1 in bytes.
+pub const DIGEST_SIZE: usize = 20;
+
+/// The registers before anything is hashed.
+pub const INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+/// Incremental SHA-1, with its registers exposed.
+#[derive(Clone, Debug)]
+pub struct Sha1 {
+    inner: HashState<5>,
+}
+
+impl Sha1 {
+    pub fn new() -> Self {
+        Self {
+            inner: HashState::new(INITIAL_STATE, Endian::Big, compress),
+        }
+    }
+
+    /// Continues from `state` as if `len` bytes, padding included,
+    /// had already been hashed. `len` must be a multiple of the block size.
+    pub fn from_state(state: [u32; 5], len: u64) -> Result<Self> {
+        Ok(Self {
+            inner: HashState::new(state, Endian::Big, compress).with_len(len)?,
+        })
+    }
+
+    /// Hashes `data` in one go.
+    pub fn digest(data: &[u8]) -> Vec<u8> {
+        let mut sha1 = Self::new();
+        sha1.update(data);
+        sha1.finalize()
+    }
+
+    /// The current registers.
+    pub fn state(&self) -> [u32; 5] {
+        self.inner.state()
+    }
+
+    /// Number of bytes hashed so far.
+    pub fn len(&self) -> u64 {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data)
+    }
+
+    /// Pads the input and returns the digest.
+    pub fn finalize(self) -> Vec<u8> {
+        self.inner.finalize()
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The padding appended to a message of `len` bytes before it is hashed:
+/// a one bit, zeros, and the length in bits as a big-endian 64 bit number.
+pub fn padding(len: u64) -> Vec<u8> {
+    state::padding(len, Endian::Big)
+}
+
+/// Splits a digest into the registers that produced it.
+pub fn digest_to_state(digest: &[u8]) -> Result<[u32; 5]> {
+    state::digest_to_state(digest, Endian::Big)
+}
+
+/// Joins the registers into a digest.
+pub fn state_to_digest(state: &[u32; 5]) -> Vec<u8> {
+    state::state_to_digest(state, Endian::Big)
+}
+
+/// The SHA-1 compression function, which mixes one
+/// block of `BLOCK_SIZE` bytes into `state`.
+pub fn compress(state: &mut [u32; 5], block: &[u8]) {
+    assert_eq!(block.len(), BLOCK_SIZE, "SHA-1 block must be 64 bytes");
+
+    let mut w = [0u32; 80];
+    for (i, word) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, wi) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5a827999),
+            20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+            _ => (b ^ c ^ d, 0xca62c1d6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(*wi);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::hash::{hash, MessageDigest};
+
+    fn openssl_sha1(data: &[u8]) -> Vec<u8> {
+        hash(MessageDigest::sha1(), data).unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_vectors() {
+        let vectors: [(&[u8], &str); 3] = [
+            (b"", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+        ];
+        for (data, expected) in vectors {
+            let digest: String = Sha1::digest(data)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            assert_eq!(digest, expected);
+        }
+    }
+
+    #[test]
+    fn test_matches_openssl() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 + 3) as u8).collect();
+        for len in [1, 55, 56, 63, 64, 65, 119, 120, 128, 1000] {
+            assert_eq!(
+                Sha1::digest(&data[..len]),
+                openssl_sha1(&data[..len]),
+                "length {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_update_in_parts() {
+        let data = [0x5a; 300];
+        for split in [0, 1, 63, 64, 100, 299] {
+            let mut sha1 = Sha1::new();
+            sha1.update(&data[..split]);
+            sha1.update(&data[split..]);
+            assert_eq!(sha1.len(), 300);
+            assert_eq!(sha1.finalize(), openssl_sha1(&data));
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        for len in 0..200u64 {
+            let padding = padding(len);
+            assert_eq!((len as usize + padding.len()) % BLOCK_SIZE, 0);
+            assert!(padding.len() >= 9 && padding.len() <= BLOCK_SIZE + 8);
+            assert_eq!(padding[padding.len() - 8..], (len * 8).to_be_bytes());
+        }
+
+        let len = u64::MAX;
+        assert_eq!(
+            (len % BLOCK_SIZE as u64) as usize + padding(len).len(),
+            2 * BLOCK_SIZE
+        );
+    }
+
+    #[test]
+    fn test_from_state() {
+        let message = b"comment1=cooking%20MCs;userdata=foo";
+        let digest = Sha1::digest(message);
+        let state = digest_to_state(&digest).unwrap();
+        assert_eq!(state_to_digest(&state), digest);
+
+        // Continuing from the digest is the same as hashing the
+        // padded message followed by more data.
+        let glue = padding(message.len() as u64);
+        let hashed = (message.len() + glue.len()) as u64;
+        let mut sha1 = Sha1::from_state(state, hashed).unwrap();
+        sha1.update(b";admin=true");
+
+        let mut forged = message.to_vec();
+        forged.extend_from_slice(&glue);
+        forged.extend_from_slice(b";admin=true");
+        assert_eq!(sha1.finalize(), openssl_sha1(&forged));
+
+        // The length wraps rather than overflows.
+        let mut sha1 = Sha1::from_state(state, u64::MAX - 63).unwrap();
+        sha1.update(&[0; 100]);
+        assert_eq!(sha1.len(), 36);
+        assert_eq!(sha1.finalize().len(), DIGEST_SIZE);
+
+        assert!(Sha1::from_state(state, 10).is_err());
+        assert!(digest_to_state(&digest[1..]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! The parts that MD4 and SHA-1 share: both hash 64 byte blocks into
+//! 32 bit registers and pad the same way, and differ only in their
+//! compression function and in the byte order of their words.
+
+use crate::{Error, Result};
+
+/// Block size in bytes of the hash functions in this module.
+pub const BLOCK_SIZE: usize = 64;
+
+/// Byte order of the words of a hash function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+impl Endian {
+    fn write_word(self, word: u32) -> [u8; 4] {
+        match self {
+            Endian::Big => word.to_be_bytes(),
+            Endian::Little => word.to_le_bytes(),
+        }
+    }
+
+    fn read_word(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        }
+    }
+}
+
+/// Incremental hashing with `N` registers: the registers, the input that
+/// doesn't fill a block yet and the number of bytes hashed.
+#[derive(Clone, Debug)]
+pub struct HashState<const N: usize> {
+    state: [u32; N],
+    /// Input that doesn't fill a block yet.
+    buffer: Vec<u8>,
+    /// Number of bytes hashed, including any that were
+    /// hashed before the state was set.
+    len: u64,
+    endian: Endian,
+    compress: fn(&mut [u32; N], &[u8]),
+}
+
+impl<const N: usize> HashState<N> {
+    pub fn new(state: [u32; N], endian: Endian, compress: fn(&mut [u32; N], &[u8])) -> Self {
+        Self {
+            state,
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+            len: 0,
+            endian,
+            compress,
+        }
+    }
+
+    /// Continues from `state` as if `len` bytes, padding included,
+    /// had already been hashed. `len` must be a multiple of the block size.
+    pub fn with_len(self, len: u64) -> Result<Self> {
+        if !len.is_multiple_of(BLOCK_SIZE as u64) {
+            return Err(Error::ArgError(format!(
+                "hashed length must be a multiple of {} but was {}",
+                BLOCK_SIZE, len
+            )));
+        }
+        Ok(Self { len, ..self })
+    }
+
+    pub fn state(&self) -> [u32; N] {
+        self.state
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        // Wraps like the 64 bit length in the padding, which `from_digest`
+        // can set close to the maximum.
+        self.len = self.len.wrapping_add(data.len() as u64);
+
+        if !self.buffer.is_empty() {
+            let n = data.len().min(BLOCK_SIZE - self.buffer.len());
+            self.buffer.extend_from_slice(&data[..n]);
+            data = &data[n..];
+            if self.buffer.len() < BLOCK_SIZE {
+                return;
+            }
+            let block = std::mem::take(&mut self.buffer);
+            (self.compress)(&mut self.state, &block);
+        }
+
+        let mut blocks = data.chunks_exact(BLOCK_SIZE);
+        for block in &mut blocks {
+            (self.compress)(&mut self.state, block);
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    /// Pads the input and returns the digest.
+    pub fn finalize(mut self) -> Vec<u8> {
+        let padding = padding(self.len, self.endian);
+        self.update(&padding);
+        state_to_digest(&self.state, self.endian)
+    }
+}
+
+/// The padding appended to a message of `len` bytes before it is hashed:
+/// a one bit, zeros, and the length in bits as a 64 bit number.
+pub fn padding(len: u64, endian: Endian) -> Vec<u8> {
+    let used = (len % BLOCK_SIZE as u64) as usize + 8;
+    let zeros = (BLOCK_SIZE - 1 - used % BLOCK_SIZE) % BLOCK_SIZE;
+    let mut padding = vec![0x80];
+    padding.resize(1 + zeros, 0);
+    let bits = len.wrapping_mul(8);
+    match endian {
+        Endian::Big => padding.extend_from_slice(&bits.to_be_bytes()),
+        Endian::Little => padding.extend_from_slice(&bits.to_le_bytes()),
+    }
+    padding
+}
+
+/// Splits a digest into the registers that produced it.
+pub fn digest_to_state<const N: usize>(digest: &[u8], endian: Endian) -> Result<[u32; N]> {
+    if digest.len() != 4 * N {
+        return Err(Error::ArgError(format!(
+            "invalid digest length: must be {} but was {}",
+            4 * N,
+            digest.len()
+        )));
+    }
+    let mut state = [0u32; N];
+    for (s, word) in state.iter_mut().zip(digest.chunks_exact(4)) {
+        *s = endian.read_word([word[0], word[1], word[2], word[3]]);
+    }
+    Ok(state)
+}
+
+/// Joins the registers into a digest.
+pub fn state_to_digest<const N: usize>(state: &[u32; N], endian: Endian) -> Vec<u8> {
+    state.iter().flat_map(|s| endian.write_word(*s)).collect()
+}
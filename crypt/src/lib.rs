@@ -1,14 +1,19 @@
 use crate::encoding::Decoder;
 use crate::op::*;
+use crate::score::{ChiSquared, Scorer};
 use openssl::error::ErrorStack;
-use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
 use std::str::from_utf8;
 
 pub mod aes;
+pub mod attack;
 pub mod encoding;
 pub mod gen;
+pub mod ghash;
+pub mod hash;
 pub mod op;
 pub mod pad;
+pub mod score;
 pub mod util;
 
 #[derive(Debug)]
@@ -52,106 +57,124 @@ macro_rules! data_err {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// A candidate solution found when breaking a cipher.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub key: u8,
+    pub plaintext: Vec<u8>,
+    /// Score of the plaintext, where higher is better.
+    pub score: f64,
+}
+
+/// Same as `Candidate` but for ciphers with longer keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyCandidate {
+    pub key: Vec<u8>,
+    pub plaintext: Vec<u8>,
+    pub score: f64,
+}
+
 pub struct Hacker {
     pub hex: encoding::hex::Hex,
     pub b64: encoding::base64::Base64,
-    ascii_digits: Vec<u8>,
-    common_letters: HashMap<char, usize>,
+    scorer: Box<dyn Scorer>,
 }
 
 impl Hacker {
+    /// Returns a hacker that ranks candidate plaintexts
+    /// with a chi-squared test against English.
     pub fn new() -> Self {
-        let mut ascii_digits: Vec<u8> = Vec::new(); // Decimal values of ASCII chars
-        for i in 32..=123 {
-            ascii_digits.push(i);
-        }
-
-        // Letters sorted after the most frequent, and
-        // more frequent means higher weight/score.
-        let most_common_letters = "etaoinshrdlcumwfgypbvkjxqz";
-        let mut weight = most_common_letters.len();
-        let mut common_letters = HashMap::new();
-        for ch in most_common_letters.chars() {
-            common_letters.insert(ch, weight);
-            weight -= 1;
-        }
+        Self::with_scorer(ChiSquared::english())
+    }
 
+    /// Returns a hacker that ranks candidate plaintexts with `scorer`.
+    pub fn with_scorer<S: Scorer + 'static>(scorer: S) -> Self {
         Self {
             hex: encoding::hex::Hex::new(),
             b64: encoding::base64::Base64::new(),
-            ascii_digits,
-            common_letters,
+            scorer: Box::new(scorer),
         }
     }
 
     // Tries to crack a hex encoded string by assuming a
     // single character key. If a solution was possible
-    // it returns the message with the highest score, i.e
-    // the message with the highest frequency of letters
-    // a-z and A-Z.
+    // it returns the valid UTF-8 message with the highest score.
     pub fn crack_single_char_xor(&self, input_hex: &str) -> Option<String> {
         let input_plain_bytes: Vec<u8> = match self.hex.decode(input_hex) {
             Ok(b) => b,
             Err(_) => return None, // No solution possible
         };
 
-        let mut results = Vec::new();
-        for ascii in &self.ascii_digits {
-            let xor = xor_with(&input_plain_bytes, ascii.to_owned());
-            match String::from_utf8(xor) {
-                Ok(s) => results.push(s),
-                Err(_) => {}
-            }
-        }
-
-        match self.max_letter_score(&results) {
-            Some(s) => Some(s.to_owned()),
-            None => None,
-        }
+        self.crack_single_byte_xor(&input_plain_bytes, 256)
+            .into_iter()
+            .find_map(|c| String::from_utf8(c.plaintext).ok())
     }
 
-    fn find_key(&self, input: &Vec<u8>) -> Option<u8> {
-        let mut max = 0;
-        let mut key: Option<u8> = None;
-
-        for ascii in &self.ascii_digits {
-            let xor = xor_with(input, ascii.to_owned());
-            if let Ok(s) = String::from_utf8(xor) {
-                let s = self.letter_score(&s);
-                if s > max {
-                    max = s;
-                    key = Some(ascii.to_owned());
-                }
-            }
-        }
+    /// Tries every key of single-byte XOR on `data` and returns the `n`
+    /// candidates with the highest score, best first.
+    pub fn crack_single_byte_xor(&self, data: &[u8], n: usize) -> Vec<Candidate> {
+        self.crack_byte(data, n, |plaintext| self.scorer.score(plaintext))
+    }
 
-        key
+    /// Like `crack_single_byte_xor`, but for a column of bytes that weren't
+    /// next to each other in the plaintext, such as every `n`th byte of
+    /// repeating-key XOR.
+    pub fn crack_column(&self, column: &[u8], n: usize) -> Vec<Candidate> {
+        self.crack_byte(column, n, |plaintext| self.scorer.score_column(plaintext))
     }
 
-    // Calculates a score based on letters (a-z, A-Z) in s.
-    pub fn letter_score(&self, s: &str) -> usize {
-        let mut total = 0;
-        for ch in s.chars() {
-            if let Some(n) = self.common_letters.get(&ch) {
-                total += n
-            }
-        }
-        total
+    fn crack_byte<F>(&self, data: &[u8], n: usize, score: F) -> Vec<Candidate>
+    where
+        F: Fn(&[u8]) -> f64,
+    {
+        let mut candidates: Vec<Candidate> = (0..=255u8)
+            .map(|key| {
+                let plaintext = xor_with(data, key);
+                let score = score(&plaintext);
+                Candidate {
+                    key,
+                    plaintext,
+                    score,
+                }
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        candidates.truncate(n);
+        candidates
     }
 
-    pub fn max_letter_score<'a>(&self, v: &'a Vec<String>) -> Option<&'a String> {
-        let mut found: Option<&'a String> = None;
+    /// Finds the lines most likely to be encrypted with single-byte XOR,
+    /// e.g. among lines read with `util::read_lines` and decoded. Returns
+    /// the `n` best candidates over all lines together with the index of
+    /// the line, best first.
+    pub fn find_single_byte_xor<T: AsRef<[u8]>>(
+        &self,
+        lines: &[T],
+        n: usize,
+    ) -> Vec<(usize, Candidate)> {
+        let mut candidates: Vec<(usize, Candidate)> = lines
+            .iter()
+            .enumerate()
+            .flat_map(|(i, line)| {
+                self.crack_single_byte_xor(line.as_ref(), n)
+                    .into_iter()
+                    .map(move |c| (i, c))
+            })
+            .collect();
 
-        let mut max_score = 0;
-        for r in v {
-            let s = self.letter_score(r);
-            if s > max_score {
-                found = Some(r);
-                max_score = s;
-            }
-        }
+        candidates.sort_by(|(_, a), (_, b)| b.score.total_cmp(&a.score));
+        candidates.truncate(n);
+        candidates
+    }
 
-        found
+    /// Returns the string in `v` with the highest score.
+    pub fn max_score<'a>(&self, v: &'a [String]) -> Option<&'a String> {
+        v.iter().max_by(|a, b| {
+            let a = self.scorer.score(a.as_bytes());
+            let b = self.scorer.score(b.as_bytes());
+            a.total_cmp(&b)
+        })
     }
 
     /// Encodes `message` using `key` with repeating-key XOR.
@@ -173,84 +196,217 @@ impl Hacker {
     /// If the key was correct, the encrypted message should be possible to
     /// break with `repeating-key-xor(message, key)`.
     pub fn break_repeating_key_xor(&self, message: &str) -> Result<(String, String)> {
-        // 29 is the correct key size
-        let key_size = match find_key_size(message) {
-            Some(k) => k,
-            None => return Err(Error::DataError(format!("failed to find any key size"))),
-        };
+        let (plaintext, key) = self.break_repeating_key_xor_bytes(message.as_bytes(), 2..=40, 3)?;
+        Ok((
+            from_utf8(&plaintext)?.to_string(),
+            from_utf8(&key)?.to_string(),
+        ))
+    }
 
-        // 5. Now that you probably know the KEYSIZE: break the ciphertext into blocks of KEYSIZE length.
-        // 6. Now transpose the blocks: make a block that is the first byte of every block,
-        //    and a block that is the second byte of every block, and so on.
-        let blocks = transponse(message, key_size);
+    /// Breaks repeating-key XOR on `data`, trying the `candidates` most
+    /// likely key sizes in `key_sizes`. Each key size is broken as
+    /// single-byte XOR per key byte and the decryption with the highest
+    /// score wins. Returns the plaintext and the key.
+    pub fn break_repeating_key_xor_bytes(
+        &self,
+        data: &[u8],
+        key_sizes: RangeInclusive<usize>,
+        candidates: usize,
+    ) -> Result<(Vec<u8>, Vec<u8>)> {
+        match self
+            .crack_repeating_key_xor(data, key_sizes, candidates)
+            .into_iter()
+            .next()
+        {
+            Some(c) => Ok((c.plaintext, c.key)),
+            None => Err(Error::DataError(
+                "failed to find any key size: the data is too short".to_string(),
+            )),
+        }
+    }
 
-        // 7. Solve each block as if it was single-character XOR.
-        let keys: Vec<u8> = blocks
-            .iter()
-            .filter_map(|block| self.find_key(block))
-            .collect();
+    /// Same as `break_repeating_key_xor_bytes`, but returns the solution
+    /// for each of the key sizes tried, best first. Key sizes that give
+    /// the same key, like multiples of the key size, are only included once.
+    pub fn crack_repeating_key_xor(
+        &self,
+        data: &[u8],
+        key_sizes: RangeInclusive<usize>,
+        candidates: usize,
+    ) -> Vec<KeyCandidate> {
+        let mut found: Vec<KeyCandidate> = Vec::new();
+        for key_size in rank_key_sizes(data, key_sizes).into_iter().take(candidates) {
+            let key = self.reduce_key(data, self.solve_key(data, key_size));
+            if found.iter().any(|c| c.key == key) {
+                continue;
+            }
+
+            let plaintext = self.repeating_key_xor(data, &key).unwrap();
+            let score = self.scorer.score(&plaintext);
+            found.push(KeyCandidate {
+                key,
+                plaintext,
+                score,
+            });
+        }
 
-        let key = from_utf8(&keys)?;
-        let a = self.repeating_key_xor(message.as_bytes(), key.as_bytes())?;
-        Ok((from_utf8(&a)?.to_string(), key.to_string()))
+        found.sort_by(|a, b| b.score.total_cmp(&a.score));
+        found
+    }
+
+    /// Finds the repeating key of `key_size` bytes used to encrypt `data`.
+    fn solve_key(&self, data: &[u8], key_size: usize) -> Vec<u8> {
+        // Transpose the blocks: make a block that is the first byte of every
+        // block, and a block that is the second byte of every block, and so on.
+        // Then solve each block as if it was single-byte XOR.
+        (0..key_size)
+            .map(|i| {
+                let column: Vec<u8> = data.iter().skip(i).step_by(key_size).copied().collect();
+                self.crack_column(&column, 1)[0].key
+            })
+            .collect()
     }
-}
 
-fn find_key_size(message: &str) -> Option<usize> {
-    let mut distances: Vec<(usize, f32)> = Vec::new();
+    /// A multiple of the key size gives the key repeated, apart from a few
+    /// bytes that may be solved differently with less data in each column.
+    /// Returns the shortest key that agrees with most of `key` when repeated.
+    fn reduce_key(&self, data: &[u8], key: Vec<u8>) -> Vec<u8> {
+        for size in (1..key.len()).filter(|size| key.len().is_multiple_of(*size)) {
+            let short = self.solve_key(data, size);
+            let matching = key
+                .iter()
+                .zip(short.iter().cycle())
+                .filter(|(a, b)| a == b)
+                .count();
+            if matching * 4 >= key.len() * 3 {
+                return short;
+            }
+        }
+        key
+    }
+}
 
-    for size in 2..=40 {
-        let mut text = message;
-        let mut v = Vec::new();
+/// Returns the key sizes in `range` ordered by how likely they are.
+///
+/// The bytes of two blocks encrypted with the same key differ in fewer
+/// bits, on average, than random bytes, so the most likely key size has
+/// the smallest normalized edit distance between consecutive blocks.
+/// Sizes with fewer than two blocks of data are left out.
+fn rank_key_sizes(data: &[u8], range: RangeInclusive<usize>) -> Vec<usize> {
+    let mut distances: Vec<(usize, f64)> = Vec::new();
+    for size in range.filter(|size| *size > 0) {
+        let blocks: Vec<&[u8]> = data.chunks_exact(size).collect();
+        if blocks.len() < 2 {
+            continue;
+        }
 
-        while text.len() > size * 2 {
-            let first = &text[0..size];
-            let second = &text[size..(2 * size)];
+        let total: usize = blocks
+            .windows(2)
+            .map(|w| hamming_dist_bytes(w[0], w[1]))
+            .sum();
+        let pairs = (blocks.len() - 1) as f64;
+        distances.push((size, total as f64 / pairs / size as f64));
+    }
 
-            // Normalized distance
-            let d = (hamming_dist(first, second) as f32) / (size as f32);
-            v.push(d);
+    distances.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    distances.into_iter().map(|(size, _)| size).collect()
+}
 
-            text = &text[(2 * size)..];
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &[u8] = b"Cooking MC's like a pound of bacon";
+    const LONG_MESSAGE: &[u8] = b"It was late in the afternoon when the train finally pulled \
+        into the small station at the edge of the town. The platform was almost empty, and the \
+        few people who had been waiting there looked up only briefly before going back to their \
+        newspapers. She stepped down with her single bag, stood still for a moment, and tried \
+        to remember the last time she had seen the place.";
+
+    #[test]
+    fn test_crack_single_byte_xor() {
+        // Keys outside of the printable range too
+        for key in [0x00, 0x58, 0x9c, 0xff] {
+            let encrypted = xor_with(MESSAGE, key);
+            let candidates = Hacker::new().crack_single_byte_xor(&encrypted, 3);
+
+            assert_eq!(candidates.len(), 3);
+            assert_eq!(candidates[0].key, key);
+            assert_eq!(candidates[0].plaintext, MESSAGE);
+            assert!(candidates[0].score >= candidates[1].score);
+            assert!(candidates[1].score >= candidates[2].score);
         }
-
-        let mean: f32 = v.iter().sum::<f32>() / v.len() as f32;
-        distances.push((size, mean));
     }
 
-    distances.sort_by(|(_, d1), (_, d2)| d1.partial_cmp(d2).unwrap());
+    #[test]
+    fn test_crack_single_byte_xor_all() {
+        let candidates = Hacker::new().crack_single_byte_xor(b"abc", 1000);
+        assert_eq!(candidates.len(), 256);
+        assert!(Hacker::new().crack_single_byte_xor(b"abc", 0).is_empty());
+    }
 
-    match distances.get(0) {
-        Some((k, _)) => Some(*k),
-        None => return None,
+    #[test]
+    fn test_break_repeating_key_xor_bytes() {
+        let hacker = Hacker::new();
+        for key in [
+            &b"ICE"[..],
+            &[0x00, 0xff, 0x80, 0x7f, 0xc3],
+            b"\xc3\xa9t\xc3\xa9",
+        ] {
+            let encrypted = hacker.repeating_key_xor(LONG_MESSAGE, key).unwrap();
+            let (plaintext, found) = hacker
+                .break_repeating_key_xor_bytes(&encrypted, 2..=10, 3)
+                .unwrap();
+            assert_eq!(found, key);
+            assert_eq!(plaintext, LONG_MESSAGE);
+        }
     }
-}
 
-fn transponse(message: &str, size: usize) -> Vec<Vec<u8>> {
-    // Break the message into blocks of `size` length.
-    let chunks: Vec<Vec<u8>> = message
-        .chars()
-        .collect::<Vec<char>>()
-        .chunks(size)
-        .map(|c| c.iter().collect::<String>().bytes().collect::<Vec<u8>>())
-        .collect();
-
-    // Make a block that is the first byte of every block,
-    // and a block that is the second byte of every block, and so on.
-    let mut map: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
-    for n in 0..size {
-        for v in &chunks {
-            if let Some(b) = v.get(n) {
-                match map.get_mut(&n) {
-                    Some(v) => v.push(*b),
-                    None => {
-                        let v = vec![*b];
-                        map.insert(n, v);
-                    }
-                }
-            }
+    #[test]
+    fn test_crack_repeating_key_xor() {
+        let hacker = Hacker::new();
+        let encrypted = hacker.repeating_key_xor(LONG_MESSAGE, b"ICE").unwrap();
+
+        let candidates = hacker.crack_repeating_key_xor(&encrypted, 2..=12, 6);
+        assert!(!candidates.is_empty() && candidates.len() <= 6);
+        assert_eq!(candidates[0].key, b"ICE");
+        assert_eq!(candidates[0].plaintext, LONG_MESSAGE);
+        for pair in candidates.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+            assert_ne!(pair[0].key, pair[1].key);
         }
     }
 
-    map.into_iter().map(|(_, v)| v).collect()
+    #[test]
+    fn test_break_repeating_key_xor_bytes_too_short() {
+        let res = Hacker::new().break_repeating_key_xor_bytes(b"abc", 2..=40, 3);
+        assert!(res.is_err());
+        let res = Hacker::new().break_repeating_key_xor_bytes(b"abcdef", 0..=0, 3);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_rank_key_sizes() {
+        let data = Hacker::new()
+            .repeating_key_xor(&[b'a'; 64], b"\x01\x02\x04\x08\x10")
+            .unwrap();
+        let sizes = rank_key_sizes(&data, 1..=40);
+        assert_eq!(sizes.len(), 32);
+        assert_eq!(sizes[0], 5);
+    }
+
+    #[test]
+    fn test_find_single_byte_xor() {
+        let lines = vec![
+            vec![0x8f, 0x12, 0x44, 0xa0, 0x3b, 0x77, 0x01, 0xfe, 0x5c, 0xd2],
+            xor_with(MESSAGE, 0xaa),
+            vec![0x13, 0xe7, 0x69, 0x90, 0x2d, 0xc4, 0x58, 0xb1, 0x0f, 0x7a],
+        ];
+
+        let found = Hacker::new().find_single_byte_xor(&lines, 2);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, 1);
+        assert_eq!(found[0].1.key, 0xaa);
+        assert_eq!(found[0].1.plaintext, MESSAGE);
+    }
 }
@@ -25,12 +25,16 @@ pub fn xor_with(a: &[u8], n: u8) -> Vec<u8> {
 }
 
 pub fn hamming_dist(a: &str, b: &str) -> usize {
-    let mut count = 0;
-    for (x, y) in a.bytes().zip(b.bytes()) {
-        let r = x ^ y;
-        count += format!("{:b}", r).chars().filter(|c| *c == '1').count();
-    }
-    count
+    hamming_dist_bytes(a.as_bytes(), b.as_bytes())
+}
+
+/// Number of differing bits between `a` and `b`,
+/// up to the length of the shortest.
+pub fn hamming_dist_bytes(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x ^ y).count_ones() as usize)
+        .sum()
 }
 
 #[cfg(test)]
@@ -40,5 +44,7 @@ mod tests {
     #[test]
     fn test_hamming_distance() {
         assert_eq!(37, hamming_dist("this is a test", "wokka wokka!!!"));
+        assert_eq!(37, hamming_dist_bytes(b"this is a test", b"wokka wokka!!!"));
+        assert_eq!(8, hamming_dist_bytes(&[0xff, 0x00], &[0x00]));
     }
 }
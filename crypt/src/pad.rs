@@ -1,5 +1,91 @@
 use crate::{Error::*, Result};
 
+/// Padding schemes for block cipher modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    /// N bytes with the value N. Always adds at least one byte.
+    Pkcs7,
+    /// Zero bytes up to the block boundary, nothing if already aligned.
+    /// Removing it also removes any trailing zeros of the data itself.
+    Zero,
+    /// ISO/IEC 7816-4: a 0x80 byte followed by zeros. Always adds at least one byte.
+    Iso7816,
+    /// ANSI X9.23: zeros followed by a byte with the number of padding bytes.
+    /// Always adds at least one byte.
+    AnsiX923,
+    /// No padding; the data must already be a multiple of the block size.
+    None,
+}
+
+impl Padding {
+    /// Pads `data` to a multiple of `size`. Returns an error if `size` is
+    /// zero, or too large for the padding length to fit in a byte.
+    pub fn pad(&self, data: &[u8], size: usize) -> Result<Vec<u8>> {
+        let max_size = match self {
+            Padding::Pkcs7 | Padding::AnsiX923 => u8::MAX as usize,
+            _ => usize::MAX,
+        };
+        if size == 0 || size > max_size {
+            return Err(ArgError(format!(
+                "invalid block size for {:?} padding: {}",
+                self, size
+            )));
+        }
+
+        let n = size - data.len() % size;
+        let mut v = data.to_vec();
+        match self {
+            Padding::Pkcs7 => v.extend(vec![n as u8; n]),
+            Padding::Zero if n != size => v.extend(vec![0; n]),
+            Padding::Zero | Padding::None => {}
+            Padding::Iso7816 => {
+                v.push(0x80);
+                v.extend(vec![0; n - 1]);
+            }
+            Padding::AnsiX923 => {
+                v.extend(vec![0; n - 1]);
+                v.push(n as u8);
+            }
+        }
+        Ok(v)
+    }
+
+    /// Validates and removes the padding from `data`, which must be
+    /// a multiple of `size`.
+    pub fn unpad<'a>(&self, data: &'a [u8], size: usize) -> Result<&'a [u8]> {
+        if size == 0 || !data.len().is_multiple_of(size) {
+            return Err(DataError(format!("data not multiple of {}", size)));
+        }
+
+        let last_block = &data[data.len().saturating_sub(size)..];
+        let n = match self {
+            Padding::None => 0,
+            Padding::Zero => last_block.iter().rev().take_while(|b| **b == 0).count(),
+            Padding::Pkcs7 => return pkcs7_unpad(data, size),
+            Padding::Iso7816 => {
+                let zeros = last_block.iter().rev().take_while(|b| **b == 0).count();
+                if zeros == last_block.len() || last_block[last_block.len() - zeros - 1] != 0x80 {
+                    return Err(DataError("invalid padding".to_string()));
+                }
+                zeros + 1
+            }
+            Padding::AnsiX923 => {
+                let n = last_block.last().map_or(0, |b| *b as usize);
+                if n == 0 || n > last_block.len() {
+                    return Err(DataError("invalid padding".to_string()));
+                }
+                let (_, pad) = last_block.split_at(last_block.len() - n);
+                if pad[..n - 1].iter().any(|b| *b != 0) {
+                    return Err(DataError("invalid padding".to_string()));
+                }
+                n
+            }
+        };
+
+        Ok(&data[..data.len() - n])
+    }
+}
+
 pub fn pkcs7(b: &[u8], size: usize) -> Vec<u8> {
     let mut v = b.to_vec();
     let n = b.len() % size;
@@ -12,37 +98,75 @@ pub fn pkcs7(b: &[u8], size: usize) -> Vec<u8> {
 
 /// Validate the block has valid PKSC#7 padding.
 /// If so, the data is returned without the padding.
-pub fn pkcs7_validate<'a>(block: &'a str, size: usize) -> Result<&'a str> {
-    if block.len() == 0 || size == 0 {
-        return Err(DataError("empty block or size".to_string()));
-    } else if block.len() != size {
-        return Err(DataError(format!(
-            "invalid size of data: expected {} but was {}",
-            size,
-            block.len()
-        )));
+pub fn pkcs7_validate(block: &str, size: usize) -> Result<&str> {
+    let data = pkcs7_unpad(block.as_bytes(), size)?;
+    // The padding bytes are ASCII, so this is always a char boundary.
+    Ok(&block[..data.len()])
+}
+
+/// Validates the PKCS#7 padding of `data`, which may span several
+/// blocks of `size` bytes, and returns the data without it.
+///
+/// The padding must be between 1 and `size` bytes, so data that isn't
+/// padded is always rejected.
+pub fn pkcs7_unpad(data: &[u8], size: usize) -> Result<&[u8]> {
+    check_padded_len(data, size)?;
+
+    let n = data[data.len() - 1] as usize;
+    if n == 0 || n > size {
+        return Err(DataError("invalid padding".to_string()));
     }
 
-    let last_char = block.chars().last().unwrap();
-    let ran = '\x01'..'\x0f';
-    if !ran.contains(&last_char) {
-        return Ok(block);
+    let (unpadded, pad) = data.split_at(data.len() - n);
+    if pad.iter().any(|b| *b as usize != n) {
+        return Err(DataError("invalid padding".to_string()));
     }
+    Ok(unpadded)
+}
+
+/// Same as `pkcs7_unpad` but the time taken doesn't depend on the value
+/// of the padding: the whole last block is always inspected, without
+/// branching on its contents. Useful when building a realistic padding
+/// oracle, where only the result should leak.
+pub fn pkcs7_unpad_ct(data: &[u8], size: usize) -> Result<&[u8]> {
+    check_padded_len(data, size)?;
 
-    let mut bytes = block.as_bytes().to_vec();
-    bytes.reverse();
+    let last_block = &data[data.len() - size..];
+    let n = last_block[size - 1];
 
-    let last_byte = &bytes[0];
-    let count = bytes.iter().filter(|b| *b == last_byte).count();
+    // 0xff for a bad padding length, i.e. 0 or larger than the block.
+    let mut bad = ct_lt(n, 1) | ct_lt(size as u8, n);
+    for (i, b) in last_block.iter().rev().enumerate() {
+        // Only the last n bytes have to be equal to n.
+        bad |= ct_lt(i as u8, n) & (b ^ n);
+    }
 
-    if *last_byte as usize == count {
-        let index = block.len() - count as usize;
-        Ok(&block[0..index])
+    if bad == 0 {
+        Ok(&data[..data.len() - n as usize])
     } else {
         Err(DataError("invalid padding".to_string()))
     }
 }
 
+/// Returns 0xff if `a < b`, else 0, without branching.
+fn ct_lt(a: u8, b: u8) -> u8 {
+    ((a as u16).wrapping_sub(b as u16) >> 8) as u8
+}
+
+fn check_padded_len(data: &[u8], size: usize) -> Result<()> {
+    if size == 0 || size > 255 {
+        return Err(DataError(format!("invalid block size: {}", size)));
+    }
+    if data.is_empty() || !data.len().is_multiple_of(size) {
+        return Err(DataError(format!(
+            "invalid size of data: expected a multiple of {} but was {}",
+            size,
+            data.len()
+        )));
+    }
+    Ok(())
+}
+
 pub fn pkcs7_inplace(b: &mut Vec<u8>, size: usize) {
     let n = b.len() % size;
     if n != 0 {
@@ -71,12 +195,75 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_padding_pad() {
+        let tests = [
+            (Padding::Pkcs7, "test", "test\x04\x04\x04\x04"),
+            (
+                Padding::Pkcs7,
+                "testtest",
+                "testtest\x08\x08\x08\x08\x08\x08\x08\x08",
+            ),
+            (Padding::Zero, "test", "test\x00\x00\x00\x00"),
+            (Padding::Zero, "testtest", "testtest"),
+            (Padding::Iso7816, "test", "test\u{80}\x00\x00\x00"),
+            (Padding::AnsiX923, "test!", "test!\x00\x00\x03"),
+            (Padding::None, "testtest", "testtest"),
+        ];
+        for (padding, data, expected) in tests {
+            let expected: Vec<u8> = expected.chars().map(|c| c as u8).collect();
+            let padded = padding.pad(data.as_bytes(), 8).unwrap();
+            assert_eq!(padded, expected);
+
+            let unpadded = padding.unpad(&padded, 8).unwrap();
+            assert_eq!(unpadded, data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_padding_pad_invalid_size() {
+        for padding in [
+            Padding::Pkcs7,
+            Padding::Zero,
+            Padding::Iso7816,
+            Padding::None,
+        ] {
+            assert!(padding.pad(b"test", 0).is_err());
+        }
+        assert!(Padding::Pkcs7.pad(b"test", 256).is_err());
+        assert!(Padding::AnsiX923.pad(b"test", 256).is_err());
+        assert_eq!(Padding::Pkcs7.pad(b"test", 255).unwrap().len(), 255);
+        assert_eq!(Padding::Iso7816.pad(b"test", 256).unwrap().len(), 256);
+    }
+
+    #[test]
+    fn test_padding_unpad_invalid() {
+        let tests = [
+            (Padding::Pkcs7, b"test\x04\x04\x04\x03".to_vec()),
+            (Padding::Pkcs7, b"testtes\x00".to_vec()),
+            (Padding::Pkcs7, b"testtes\x09".to_vec()),
+            (Padding::Iso7816, b"test\x00\x00\x00\x00".to_vec()),
+            (Padding::Iso7816, b"test\x01\x00\x00\x00".to_vec()),
+            (Padding::AnsiX923, b"test\x00\x01\x00\x04".to_vec()),
+            (Padding::AnsiX923, b"testtes\x00".to_vec()),
+            (Padding::None, b"test".to_vec()),
+        ];
+        for (padding, data) in tests {
+            assert!(
+                padding.unpad(&data, 8).is_err(),
+                "{:?}: {:?}",
+                padding,
+                data
+            );
+        }
+    }
+
     #[test]
     fn test_pkcs7_validate() {
         let tests = [
             ("test", "test\x04\x04\x04\x04"),
-            ("testtest", "testtest"),
             ("test!", "test!\x03\x03\x03"),
+            ("testtest", "testtest\x08\x08\x08\x08\x08\x08\x08\x08"),
         ];
         for (expected, padded) in tests {
             let actual = pkcs7_validate(padded, 8).unwrap();
@@ -86,7 +273,12 @@ mod tests {
 
     #[test]
     fn test_pkcs7_validate_invalid_padding() {
-        let tests = ["test\x01", "test\x04\x04\x04", "test\x04\x04\x04"];
+        let tests = [
+            "test\x01",
+            "test\x04\x04\x04",
+            "testtest",
+            "test\x01\x02\x03\x04",
+        ];
         for t in tests {
             let res = pkcs7_validate(t, 8);
             assert!(res.is_err());
@@ -101,4 +293,54 @@ mod tests {
             assert!(res.is_err());
         }
     }
+
+    #[test]
+    fn test_pkcs7_unpad() {
+        let tests: [(&[u8], &[u8]); 4] = [
+            (b"YELLOW SUBMARINE\x04\x04\x04\x04", b"YELLOW SUBMARINE"),
+            (b"test\x04\x04\x04\x04", b"test"),
+            (b"\x04\x04\x04\x04", b""),
+            (b"ICE ICE BABY\x01\x01\x02\x02", b"ICE ICE BABY\x01\x01"),
+        ];
+        for (padded, expected) in tests {
+            assert_eq!(pkcs7_unpad(padded, 4).unwrap(), expected);
+            assert_eq!(pkcs7_unpad_ct(padded, 4).unwrap(), expected);
+        }
+
+        // A whole block of padding
+        let padded =
+            b"YELLOW SUBMARINE\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10";
+        assert_eq!(pkcs7_unpad(padded, 16).unwrap(), b"YELLOW SUBMARINE");
+        assert_eq!(pkcs7_unpad_ct(padded, 16).unwrap(), b"YELLOW SUBMARINE");
+    }
+
+    #[test]
+    fn test_pkcs7_unpad_invalid() {
+        let tests: [(&[u8], usize); 8] = [
+            (b"ICE ICE BABY\x05\x05\x05\x05", 16),
+            (b"ICE ICE BABY\x01\x02\x03\x04", 16),
+            (b"ICE ICE BABY\x04\x04\x04\x00", 16),
+            (b"testtest", 8),
+            (b"testtes\x09", 8),
+            (b"test\x04\x04\x04", 8),
+            (b"", 8),
+            (b"test\x01", 0),
+        ];
+        for (padded, size) in tests {
+            assert!(pkcs7_unpad(padded, size).is_err());
+            assert!(pkcs7_unpad_ct(padded, size).is_err());
+        }
+    }
+
+    #[test]
+    fn test_pkcs7_unpad_ct_matches() {
+        // Every possible value of the last two bytes.
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                let block = [b'x', b'x', b'x', b'x', b'x', b'x', a, b];
+                let expected = pkcs7_unpad(&block, 8).ok();
+                assert_eq!(pkcs7_unpad_ct(&block, 8).ok(), expected);
+            }
+        }
+    }
 }
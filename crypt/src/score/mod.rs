@@ -0,0 +1,359 @@
+//! Scoring of how much a candidate plaintext looks like English, used to
+//! rank the candidates when breaking a cipher by brute force.
+
+use std::collections::HashMap;
+
+/// Sample of English text used by the default language models: the
+/// Declaration of Independence, the Gettysburg Address and the opening
+/// chapters of a few novels, all in the public domain.
+const CORPUS: &str = include_str!("english.txt");
+
+/// Number of symbols the text is reduced to: the 26 letters regardless
+/// of case, whitespace and any other printable character.
+const SYMBOLS: usize = 28;
+const SPACE: usize = 26;
+const OTHER: usize = 27;
+
+/// English letter frequencies, including space, from a large corpus.
+const ENGLISH_FREQUENCIES: [f64; 27] = [
+    0.0651738, 0.0124248, 0.0217339, 0.0349835, 0.1041442, 0.0197881, 0.0158610, 0.0492888,
+    0.0558094, 0.0009033, 0.0050529, 0.0331490, 0.0202124, 0.0564513, 0.0596302, 0.0137645,
+    0.0008606, 0.0497563, 0.0515760, 0.0729357, 0.0225134, 0.0082903, 0.0171272, 0.0013692,
+    0.0145984, 0.0007836, 0.1918182,
+];
+
+/// Expected share of punctuation and digits in English text.
+const OTHER_FREQUENCY: f64 = 0.02;
+
+/// Expected share of bytes that aren't printable, which is
+/// only there to give them a large penalty.
+const INVALID_FREQUENCY: f64 = 1e-6;
+
+/// Scores candidate plaintexts.
+pub trait Scorer {
+    /// Returns the score of `text`. A higher score means that
+    /// the text is more likely to be English.
+    fn score(&self, text: &[u8]) -> f64;
+
+    /// Returns the score of `column`, bytes that weren't next to each
+    /// other in the text, such as every `n`th byte of it. The default is
+    /// `score`, which suits scorers that only count symbols.
+    fn score_column(&self, column: &[u8]) -> f64 {
+        self.score(column)
+    }
+}
+
+impl<S: Scorer + ?Sized> Scorer for Box<S> {
+    fn score(&self, text: &[u8]) -> f64 {
+        (**self).score(text)
+    }
+
+    fn score_column(&self, column: &[u8]) -> f64 {
+        (**self).score_column(column)
+    }
+}
+
+/// Pearson's chi-squared test of the symbol frequencies against those
+/// of English. The score is the negated test statistic, so that text
+/// closer to English scores higher.
+pub struct ChiSquared {
+    expected: [f64; SYMBOLS + 1],
+}
+
+impl ChiSquared {
+    /// Uses the frequencies of English letters and space.
+    pub fn english() -> Self {
+        let mut expected = [0.0; SYMBOLS + 1];
+        for (e, f) in expected.iter_mut().zip(ENGLISH_FREQUENCIES) {
+            *e = f * (1.0 - OTHER_FREQUENCY);
+        }
+        expected[OTHER] = OTHER_FREQUENCY;
+        expected[SYMBOLS] = INVALID_FREQUENCY;
+        Self { expected }
+    }
+
+    /// Uses the frequencies of the symbols in `corpus`.
+    pub fn from_corpus(corpus: &[u8]) -> Self {
+        let counts = count_symbols(corpus);
+        let total = corpus.len() as f64 + SYMBOLS as f64;
+
+        let mut expected = [0.0; SYMBOLS + 1];
+        for (e, c) in expected.iter_mut().zip(counts) {
+            // Add-one smoothing, so no symbol is expected to never appear.
+            *e = (c as f64 + 1.0) / total;
+        }
+        expected[SYMBOLS] = INVALID_FREQUENCY;
+        Self { expected }
+    }
+}
+
+impl Scorer for ChiSquared {
+    fn score(&self, text: &[u8]) -> f64 {
+        let len = text.len() as f64;
+        let chi_squared: f64 = count_symbols(text)
+            .iter()
+            .zip(self.expected)
+            .map(|(observed, p)| {
+                let expected = p * len;
+                (*observed as f64 - expected).powi(2) / expected
+            })
+            .sum();
+
+        if text.is_empty() {
+            0.0
+        } else {
+            -chi_squared
+        }
+    }
+}
+
+/// Mean log-likelihood of the n-grams of the text, with probabilities
+/// estimated from a corpus. Works better than single letter frequencies
+/// on short texts, where the frequencies say little.
+pub struct Ngrams {
+    n: usize,
+    log_probs: HashMap<Vec<u8>, f64>,
+    /// Log probability of an n-gram that isn't in the corpus.
+    unseen: f64,
+    /// Log probabilities of the single symbols, for scoring columns
+    /// where the n-grams say nothing.
+    symbol_log_probs: [f64; SYMBOLS],
+}
+
+impl Ngrams {
+    /// English bigram model.
+    pub fn bigrams() -> Self {
+        Self::from_corpus(CORPUS.as_bytes(), 2)
+    }
+
+    /// English trigram model.
+    pub fn trigrams() -> Self {
+        Self::from_corpus(CORPUS.as_bytes(), 3)
+    }
+
+    /// Builds a model of `n`-grams from `corpus`.
+    pub fn from_corpus(corpus: &[u8], n: usize) -> Self {
+        let n = n.max(1);
+        let mut counts: HashMap<Vec<u8>, usize> = HashMap::new();
+        let symbols = to_symbols(corpus);
+        for gram in symbols.windows(n) {
+            if let Some(gram) = gram.iter().copied().collect::<Option<Vec<u8>>>() {
+                *counts.entry(gram).or_insert(0) += 1;
+            }
+        }
+
+        // Add-one smoothing over every possible n-gram.
+        let total = counts.values().sum::<usize>() as f64 + (SYMBOLS as f64).powi(n as i32);
+        let log_probs = counts
+            .into_iter()
+            .map(|(gram, count)| (gram, ((count + 1) as f64 / total).ln()))
+            .collect();
+
+        let symbol_counts = count_symbols(corpus);
+        let symbol_total = symbol_counts[..SYMBOLS].iter().sum::<usize>() as f64 + SYMBOLS as f64;
+        let mut symbol_log_probs = [0.0; SYMBOLS];
+        for (p, count) in symbol_log_probs.iter_mut().zip(symbol_counts) {
+            *p = ((count + 1) as f64 / symbol_total).ln();
+        }
+
+        Self {
+            n,
+            log_probs,
+            unseen: (1.0 / total).ln(),
+            symbol_log_probs,
+        }
+    }
+
+    fn log_prob(&self, gram: &[Option<u8>]) -> f64 {
+        match gram.iter().copied().collect::<Option<Vec<u8>>>() {
+            Some(gram) => *self.log_probs.get(&gram).unwrap_or(&self.unseen),
+            // Far less likely than any n-gram of printable characters.
+            None => self.unseen * 2.0,
+        }
+    }
+}
+
+impl Scorer for Ngrams {
+    fn score(&self, text: &[u8]) -> f64 {
+        // Text is assumed to be surrounded by whitespace,
+        // so the first and last letters count too.
+        let mut symbols = vec![Some(SPACE as u8)];
+        symbols.extend(to_symbols(text));
+        symbols.push(Some(SPACE as u8));
+
+        if symbols.len() < self.n {
+            return self.unseen;
+        }
+
+        let grams = symbols.windows(self.n);
+        let count = grams.len() as f64;
+        grams.map(|gram| self.log_prob(gram)).sum::<f64>() / count
+    }
+
+    fn score_column(&self, column: &[u8]) -> f64 {
+        if column.is_empty() {
+            return self.unseen;
+        }
+        let log_prob: f64 = column
+            .iter()
+            .map(|b| match symbol(*b) {
+                Some(s) => self.symbol_log_probs[s as usize],
+                None => self.unseen * 2.0,
+            })
+            .sum();
+        log_prob / column.len() as f64
+    }
+}
+
+/// Share of the bytes that are printable ASCII or whitespace.
+pub struct PrintableRatio;
+
+impl Scorer for PrintableRatio {
+    fn score(&self, text: &[u8]) -> f64 {
+        if text.is_empty() {
+            return 0.0;
+        }
+        let printable = text.iter().filter(|b| symbol(**b).is_some()).count();
+        printable as f64 / text.len() as f64
+    }
+}
+
+/// Rejects text where too few bytes are printable, by giving it the
+/// lowest possible score, and scores the rest with another scorer.
+pub struct Filtered<S> {
+    scorer: S,
+    min_ratio: f64,
+}
+
+impl<S: Scorer> Filtered<S> {
+    pub fn new(scorer: S, min_ratio: f64) -> Self {
+        Self { scorer, min_ratio }
+    }
+}
+
+impl<S: Scorer> Scorer for Filtered<S> {
+    fn score(&self, text: &[u8]) -> f64 {
+        if PrintableRatio.score(text) < self.min_ratio {
+            f64::NEG_INFINITY
+        } else {
+            self.scorer.score(text)
+        }
+    }
+}
+
+/// Maps a byte to its symbol, or `None` if it isn't printable.
+fn symbol(b: u8) -> Option<u8> {
+    let s = match b {
+        b'a'..=b'z' => (b - b'a') as usize,
+        b'A'..=b'Z' => (b - b'A') as usize,
+        b' ' | b'\n' | b'\r' | b'\t' => SPACE,
+        0x21..=0x7e => OTHER,
+        _ => return None,
+    };
+    Some(s as u8)
+}
+
+fn to_symbols(text: &[u8]) -> Vec<Option<u8>> {
+    text.iter().map(|b| symbol(*b)).collect()
+}
+
+/// Counts the symbols of `text`, with the bytes that
+/// aren't printable counted last.
+fn count_symbols(text: &[u8]) -> [usize; SYMBOLS + 1] {
+    let mut counts = [0; SYMBOLS + 1];
+    for b in text {
+        match symbol(*b) {
+            Some(s) => counts[s as usize] += 1,
+            None => counts[SYMBOLS] += 1,
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGLISH: &[u8] = b"Cooking MC's like a pound of bacon";
+
+    fn scorers() -> Vec<Box<dyn Scorer>> {
+        vec![
+            Box::new(ChiSquared::english()),
+            Box::new(ChiSquared::from_corpus(CORPUS.as_bytes())),
+            Box::new(Ngrams::bigrams()),
+            Box::new(Ngrams::trigrams()),
+            Box::new(Filtered::new(Ngrams::bigrams(), 0.9)),
+        ]
+    }
+
+    #[test]
+    fn test_english_beats_xored() {
+        for scorer in scorers() {
+            let english = scorer.score(ENGLISH);
+            for key in 1..=255u8 {
+                let xored: Vec<u8> = ENGLISH.iter().map(|b| b ^ key).collect();
+                assert!(english > scorer.score(&xored), "key {}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_english_beats_garbage() {
+        let garbage = [
+            b"xqzj vkw pfq zzx jjq".to_vec(),
+            b"!!!!!!!!!!!!!!!!!!!!".to_vec(),
+            vec![0u8; 20],
+            (0..20).collect(),
+        ];
+        let english = b"the lord of the rings";
+        for scorer in scorers() {
+            for g in &garbage {
+                assert!(scorer.score(english) > scorer.score(g));
+            }
+        }
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        for scorer in scorers() {
+            assert_eq!(scorer.score(b"Hello There"), scorer.score(b"hELLO tHERE"));
+        }
+    }
+
+    #[test]
+    fn test_printable_ratio() {
+        assert_eq!(PrintableRatio.score(b"abc\n"), 1.0);
+        assert_eq!(PrintableRatio.score(b"ab\x00\xff"), 0.5);
+        assert_eq!(PrintableRatio.score(b""), 0.0);
+
+        let filtered = Filtered::new(ChiSquared::english(), 0.75);
+        assert_eq!(filtered.score(b"ab\x00\xff"), f64::NEG_INFINITY);
+        assert!(filtered.score(b"abc\x00").is_finite());
+    }
+
+    #[test]
+    fn test_short_text() {
+        let bigrams = Ngrams::bigrams();
+        assert!(bigrams.score(b"a").is_finite());
+        assert!(bigrams.score(b"I") > bigrams.score(b"\x01"));
+
+        let fourgrams = Ngrams::from_corpus(CORPUS.as_bytes(), 4);
+        assert_eq!(fourgrams.score(b""), fourgrams.unseen);
+    }
+
+    #[test]
+    fn test_score_column() {
+        // Every third byte of a sentence, as in repeating-key XOR.
+        let column = &b"it was the best of times, it was the worst of times"
+            .iter()
+            .step_by(3)
+            .copied()
+            .collect::<Vec<u8>>();
+        for scorer in [Ngrams::bigrams(), Ngrams::trigrams()] {
+            assert!(scorer.score_column(column) > scorer.score_column(b"9$}4*1=;6%5#8"));
+            assert!(scorer.score_column(b"") < scorer.score_column(column));
+        }
+        let chi_squared = ChiSquared::english();
+        assert_eq!(chi_squared.score_column(column), chi_squared.score(column));
+    }
+}
@@ -2,7 +2,7 @@ use super::{Error, Result};
 use std::fmt::Debug;
 use std::fs::{File, OpenOptions};
 use std::io;
-use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 
 pub fn read_bytes(filename: &str) -> Result<Vec<u8>> {
     let mut reader = open(filename)?;
@@ -17,6 +17,24 @@ pub fn read_bytes(filename: &str) -> Result<Vec<u8>> {
     }
 }
 
+/// Reads at most `len` bytes starting at `offset` in the file,
+/// without reading the rest of it.
+pub fn read_range(filename: &str, offset: u64, len: usize) -> Result<Vec<u8>> {
+    let mut reader = open(filename)?;
+    if let Err(err) = reader.seek(SeekFrom::Start(offset)) {
+        return Err(Error::DataError(format!("failed to seek file: {}", err)));
+    }
+
+    let mut buf = Vec::new();
+    match reader.take(len as u64).read_to_end(&mut buf) {
+        Ok(_) => Ok(buf),
+        Err(err) => Err(Error::DataError(format!(
+            "failed to read file bytes: {}",
+            err
+        ))),
+    }
+}
+
 pub fn read_lines(filename: &str) -> Result<Vec<String>> {
     let reader = open(filename)?;
     let lines = reader
@@ -68,6 +86,14 @@ pub fn write_bytes(filename: &str, b: &[u8]) -> Result<()> {
     }
 }
 
+/// Creates, or truncates, the file and returns a buffered writer to it.
+pub fn create(filename: &str) -> Result<BufWriter<File>> {
+    match File::create(filename) {
+        Ok(f) => Ok(BufWriter::new(f)),
+        Err(err) => Err(Error::ArgError(format!("error creating file: {}", err))),
+    }
+}
+
 pub fn write_stdout(b: &[u8]) -> Result<()> {
     match io::stdout().write_all(b) {
         Ok(_) => Ok(()),
@@ -134,7 +160,7 @@ pub fn into_line(s: &str) -> String {
     lines.join("")
 }
 
-fn open(filename: &str) -> Result<BufReader<File>> {
+pub fn open(filename: &str) -> Result<BufReader<File>> {
     match File::open(filename) {
         Ok(f) => Ok(BufReader::new(f)),
         Err(err) => Err(Error::ArgError(format!("error opening file: {}", err))),
@@ -151,6 +177,16 @@ mod tests {
         assert!(b.len() > 0);
     }
 
+    #[test]
+    fn test_read_range() {
+        let b = read_bytes("Cargo.toml").unwrap();
+        let r = read_range("Cargo.toml", 5, 10).unwrap();
+        assert_eq!(r, b[5..15]);
+
+        let r = read_range("Cargo.toml", b.len() as u64 - 3, 10).unwrap();
+        assert_eq!(r, b[b.len() - 3..]);
+    }
+
     #[test]
     fn test_read_lines() {
         let lines = read_lines("Cargo.toml").unwrap();